@@ -0,0 +1,93 @@
+//! Aliases defined in the user's shell rc files, so commands are counted under what they run.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::tokenize;
+
+/// How deep aliases referring to other aliases are followed.
+pub(crate) const MAX_DEPTH: usize = 10;
+
+#[derive(Debug, Default)]
+pub struct AliasMap(HashMap<String, String>);
+
+impl AliasMap {
+    /// The aliases from `~/.zshrc` and `~/.bashrc`. If both define an alias, the zsh one wins.
+    pub fn load() -> Self {
+        let mut aliases = Self::default();
+        let Some(home) = home::home_dir() else {
+            return aliases;
+        };
+        for rc in [".bashrc", ".zshrc"] {
+            aliases.0.extend(parse_aliases(&home.join(rc)).0);
+        }
+        aliases
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// What the aliases expand to.
+    pub fn expansions(&self) -> impl Iterator<Item = &str> {
+        self.0.values().map(String::as_str)
+    }
+
+    /// Replaces an alias in the first word by its expansion, following aliases that expand to
+    /// other aliases. Like in the shell, an alias is not expanded again inside its own expansion,
+    /// so `alias ls='ls --color'` works.
+    pub fn expand(&self, words: &mut Vec<Vec<u8>>) {
+        let mut expanded = Vec::new();
+        for _ in 0..MAX_DEPTH {
+            let Some(name) = words.first().and_then(|x| std::str::from_utf8(x).ok()) else {
+                return;
+            };
+            if expanded.iter().any(|x| x == name) {
+                return;
+            }
+            let Some(expansion) = self.0.get(name) else {
+                return;
+            };
+            expanded.push(name.to_owned());
+            words.splice(..1, tokenize(expansion.as_bytes()));
+        }
+    }
+}
+
+/// Reads the `alias name=value` lines of a shell rc file. Anything more involved, like aliases
+/// defined inside functions or conditionals spanning lines, is not understood, but aliases in an
+/// indented `if` block are still found. A missing file has no aliases.
+pub fn parse_aliases(path: &Path) -> AliasMap {
+    let mut aliases = AliasMap::default();
+    let Ok(text) = std::fs::read(path) else {
+        return aliases;
+    };
+    for line in text.split(|x| *x == b'\n') {
+        let words = tokenize(line.trim_ascii());
+        let Some((b"alias", definitions)) = words.split_first().map(|(a, b)| (a.as_slice(), b))
+        else {
+            continue;
+        };
+        // quotes are already removed, so `ll='ls -l'` is now the single word `ll=ls -l`
+        for definition in definitions {
+            if definition.starts_with(b"#") {
+                break;
+            }
+            if definition.starts_with(b"-") {
+                continue;
+            }
+            let Ok(definition) = std::str::from_utf8(definition) else {
+                continue;
+            };
+            if let Some((name, expansion)) = definition.split_once('=') {
+                if !name.is_empty() {
+                    aliases.0.insert(name.to_owned(), expansion.to_owned());
+                }
+            }
+        }
+    }
+    aliases
+}
@@ -0,0 +1,260 @@
+//! Extension point for counting things the built-in statistics do not cover.
+//!
+//! Analyzers added to [`State::analyzers`](crate::State::analyzers) see every command and
+//! contribute [`Section`]s to the report. [`PodmanAnalyzer`] counts what the state does not, and shows
+//! how an analyzer can be merged with the one of another history.
+
+use std::{any::Any, collections::HashMap};
+
+use crate::{
+    container_name, container_op,
+    json::{self, ToJson},
+    most_common,
+    persist::Fields,
+    Merge,
+};
+
+/// A section of the report, printed like the built-in ones.
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    pub heading: String,
+    /// what the rows count, the heading of their column in a table
+    pub column: &'static str,
+    /// names with their counts, most frequent first
+    pub rows: Vec<(u32, String)>,
+    /// sentences printed after the rows
+    pub lines: Vec<String>,
+}
+
+pub trait Analyzer: Send {
+    /// Called for every command with its words, after environment variables, aliases and `sudo`
+    /// were stripped, and when it was started if the history records that.
+    fn process(&mut self, tokens: &[&[u8]], timestamp: Option<u64>);
+
+    /// The sections for the report, each listing at most `limit` rows. Empty if there is nothing
+    /// to report.
+    fn report(&self, limit: usize) -> Vec<Section>;
+
+    /// The analyzer as [`Any`], so [`Analyzer::merge`] can check that the other one is of the same
+    /// type. Analyzers that cannot be merged return `None`.
+    fn as_any(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+
+    /// Adds what `other` counted, if it is the same kind of analyzer, and returns whether it was.
+    /// Analyzers that are not merged each get their own section in the report.
+    fn merge(&mut self, other: &mut dyn Analyzer) -> bool {
+        let _ = other;
+        false
+    }
+
+    /// The name the statistics are saved and written to JSON under, and the section whose
+    /// `--top-<name>` limits the rows of the report. Analyzers without one are left out of both,
+    /// and those with one have to be known to [`load`] to be read back.
+    fn name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Writes what was counted, for `--save-state` and `--persist`.
+    fn save(&self, fields: &mut Fields) {
+        let _ = fields;
+    }
+
+    /// Writes what was counted as a JSON object, for `--output-json`.
+    fn write_json(&self, out: &mut String) {
+        out.push_str("{}");
+    }
+}
+
+/// The analyzer that saved `fields` under `name`, see [`Analyzer::name`]. `None` if the name is
+/// unknown or the fields cannot be decoded.
+pub(crate) fn load(name: &str, fields: &Fields) -> Option<Box<dyn Analyzer>> {
+    match name {
+        PodmanAnalyzer::NAME => Some(Box::new(PodmanAnalyzer {
+            subcommands: fields.get("subcommands")?,
+            images: fields.get("images")?,
+            containers: fields.get("containers")?,
+            docker_commands: fields.get("docker_commands")?,
+        })),
+        _ => None,
+    }
+}
+
+/// The analyzers that have a name, as a JSON object of their statistics by name.
+pub(crate) struct Named<'a>(pub &'a [Box<dyn Analyzer>]);
+
+impl ToJson for Named<'_> {
+    fn write_json(&self, out: &mut String) {
+        let mut object = json::Object::new(out);
+        for analyzer in self.0 {
+            if let Some(name) = analyzer.name() {
+                object.field(name, &Json(&**analyzer));
+            }
+        }
+        object.finish();
+    }
+}
+
+struct Json<'a>(&'a dyn Analyzer);
+
+impl ToJson for Json<'_> {
+    fn write_json(&self, out: &mut String) {
+        self.0.write_json(out);
+    }
+}
+
+/// Merges `analyzer` into the first of `analyzers` that accepts it, returning it if none does.
+pub(crate) fn merge_into(
+    analyzers: &mut [Box<dyn Analyzer>],
+    mut analyzer: Box<dyn Analyzer>,
+) -> Option<Box<dyn Analyzer>> {
+    let merged = analyzers.iter_mut().any(|x| x.merge(&mut *analyzer));
+    (!merged).then_some(analyzer)
+}
+
+/// A section listing the `limit` most frequent of `map`, `None` if it is empty.
+fn list(
+    heading: &str,
+    column: &'static str,
+    map: &HashMap<String, u32>,
+    limit: usize,
+) -> Option<Section> {
+    if map.is_empty() {
+        return None;
+    }
+    let rows = most_common(map)
+        .into_iter()
+        .take(limit)
+        .map(|(count, name)| (count, name.clone()))
+        .collect();
+    Some(Section {
+        heading: heading.to_owned(),
+        column,
+        rows,
+        lines: Vec::new(),
+    })
+}
+
+/// Podman subcommands run with `podman`, `podman-compose` and `buildah`.
+const PODMAN_TOOLS: &[&str] = &["podman", "podman-compose", "buildah"];
+
+/// Subcommands that act on an existing container, whose name is counted.
+const CONTAINER_SUBCOMMANDS: &[&str] = &[
+    "attach", "exec", "kill", "logs", "restart", "rm", "start", "stop",
+];
+
+/// Counts the subcommands of `podman`, `podman-compose` and `buildah`, the images they use and
+/// the containers they act on, and how often `docker` was run to compare.
+#[derive(Default)]
+pub struct PodmanAnalyzer {
+    /// the tool followed by the subcommand, like `podman run` or `buildah from`
+    pub subcommands: HashMap<String, u32>,
+    pub images: HashMap<String, u32>,
+    pub containers: HashMap<String, u32>,
+    pub docker_commands: u32,
+}
+
+impl PodmanAnalyzer {
+    const NAME: &'static str = "podman";
+}
+
+impl Analyzer for PodmanAnalyzer {
+    fn process(&mut self, tokens: &[&[u8]], _timestamp: Option<u64>) {
+        let Some((cmd, args)) = tokens.split_first() else {
+            return;
+        };
+        let Some(tool) = std::str::from_utf8(cmd)
+            .ok()
+            .filter(|x| PODMAN_TOOLS.contains(x))
+        else {
+            if matches!(*cmd, b"docker" | b"docker-compose") {
+                self.docker_commands += 1;
+            }
+            return;
+        };
+        let Some((subcommand, image)) = container_op(args, tool != "podman-compose") else {
+            return;
+        };
+        if let Some(image) = image {
+            *self.images.entry(image).or_default() += 1;
+        }
+        if CONTAINER_SUBCOMMANDS.contains(&subcommand.as_str()) {
+            let position = args.iter().position(|x| !x.starts_with(b"-")).unwrap_or(0);
+            if let Some(name) = container_name(&args[position + 1..]) {
+                *self.containers.entry(name.to_owned()).or_default() += 1;
+            }
+        }
+        *self
+            .subcommands
+            .entry(format!("{tool} {subcommand}"))
+            .or_default() += 1;
+    }
+
+    fn report(&self, limit: usize) -> Vec<Section> {
+        let Some(mut subcommands) = list(
+            "Your top Podman subcommands:",
+            "Subcommand",
+            &self.subcommands,
+            limit,
+        ) else {
+            return Vec::new();
+        };
+        if self.docker_commands > 0 {
+            let podman: u32 = self.subcommands.values().sum();
+            subcommands.lines.push(format!(
+                "You ran docker {} times and the Podman tools {podman}. Halfway through the migration?",
+                self.docker_commands
+            ));
+        }
+        let mut sections = vec![subcommands];
+        sections.extend(list(
+            "Your top Podman images:",
+            "Image",
+            &self.images,
+            limit,
+        ));
+        sections.extend(list(
+            "Your top Podman containers:",
+            "Container",
+            &self.containers,
+            limit,
+        ));
+        sections
+    }
+
+    fn as_any(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+
+    fn merge(&mut self, other: &mut dyn Analyzer) -> bool {
+        let Some(other) = other.as_any().and_then(|x| x.downcast_mut::<Self>()) else {
+            return false;
+        };
+        self.subcommands
+            .merge(std::mem::take(&mut other.subcommands));
+        self.images.merge(std::mem::take(&mut other.images));
+        self.containers.merge(std::mem::take(&mut other.containers));
+        self.docker_commands += other.docker_commands;
+        true
+    }
+
+    fn name(&self) -> Option<&'static str> {
+        Some(Self::NAME)
+    }
+
+    fn save(&self, fields: &mut Fields) {
+        fields.set("subcommands", &self.subcommands);
+        fields.set("images", &self.images);
+        fields.set("containers", &self.containers);
+        fields.set("docker_commands", &self.docker_commands);
+    }
+
+    fn write_json(&self, out: &mut String) {
+        json::Object::new(out)
+            .field("subcommands", &self.subcommands)
+            .field("images", &self.images)
+            .field("containers", &self.containers)
+            .field("docker_commands", &self.docker_commands)
+            .finish();
+    }
+}
@@ -0,0 +1,129 @@
+//! Placeholders for the parts of commands that identify people and machines, for `--anonymize`.
+//!
+//! The detection goes by what a word looks like, so it errs in both directions: a host name without
+//! a dot is not recognized, and a file name like `notes.io` is taken for a host.
+
+/// Top-level domains and suffixes of local networks that make a dotted word a host name.
+const HOST_SUFFIXES: &[&str] = &[
+    "com",
+    "org",
+    "net",
+    "io",
+    "dev",
+    "app",
+    "ai",
+    "co",
+    "de",
+    "uk",
+    "fr",
+    "nl",
+    "eu",
+    "us",
+    "ch",
+    "at",
+    "se",
+    "jp",
+    "cn",
+    "ru",
+    "edu",
+    "gov",
+    "cloud",
+    "local",
+    "lan",
+    "internal",
+    "localdomain",
+    "home",
+    "corp",
+];
+
+/// The placeholder for a word that looks like a URL, email address, UUID, path or host name, or
+/// `None` if it looks like none of them.
+pub fn placeholder(word: &str) -> Option<&'static str> {
+    let word = word.trim_matches(|c| matches!(c, '"' | '\'' | '(' | ')' | ',' | ';'));
+    if word.contains("://") || is_scp_target(word) {
+        Some("<url>")
+    } else if is_email(word) {
+        Some("<email>")
+    } else if is_uuid(word) {
+        Some("<uuid>")
+    } else if is_host(word)
+        || word
+            .split_once('@')
+            .is_some_and(|x| !x.0.is_empty() && is_label(x.1))
+    {
+        Some("<host>")
+    } else if word.contains('/') || word.starts_with('~') {
+        Some("<path>")
+    } else {
+        None
+    }
+}
+
+/// `text` with every word that [`placeholder`] recognizes replaced, including the values of
+/// options like `--host=example.com`.
+pub fn anonymize(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            if let Some(placeholder) = placeholder(word) {
+                return placeholder.to_owned();
+            }
+            match word.split_once('=') {
+                Some((key, value)) => match placeholder(value) {
+                    Some(placeholder) => format!("{key}={placeholder}"),
+                    None => word.to_owned(),
+                },
+                None => word.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_label(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 63
+        && s.bytes().all(|x| x.is_ascii_alphanumeric() || x == b'-')
+        && !s.starts_with('-')
+}
+
+/// A dotted host name with a known suffix, or an IPv4 address, optionally with a port.
+fn is_host(word: &str) -> bool {
+    let host = match word.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|x| x.is_ascii_digit()) => host,
+        _ => word,
+    };
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() == 4 && labels.iter().all(|x| x.parse::<u8>().is_ok()) {
+        return true;
+    }
+    labels.len() >= 2
+        && labels.iter().all(|x| is_label(x))
+        && HOST_SUFFIXES.contains(&labels[labels.len() - 1].to_ascii_lowercase().as_str())
+}
+
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    !local.is_empty() && !local.contains('/') && domain.contains('.') && is_host(domain)
+}
+
+/// `user@host:path` as understood by `scp`, `rsync` and `git`.
+fn is_scp_target(word: &str) -> bool {
+    let Some((host, path)) = word.split_once(':') else {
+        return false;
+    };
+    let host = host.rsplit_once('@').map_or(host, |x| x.1);
+    word.contains('@')
+        && !path.is_empty()
+        && !path.bytes().all(|x| x.is_ascii_digit())
+        && (is_label(host) || is_host(host))
+}
+
+fn is_uuid(word: &str) -> bool {
+    let groups: Vec<&str> = word.split('-').collect();
+    groups.iter().map(|x| x.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|x| x.bytes().all(|x| x.is_ascii_hexdigit()))
+}
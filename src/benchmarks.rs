@@ -0,0 +1,71 @@
+//! Made-up "average user" numbers for `--leaderboard`, to compare your statistics with.
+//!
+//! None of these were measured. They are rough guesses at what is typical, good enough for seeing
+//! whether you are a heavy terminal user but nothing to draw conclusions from.
+
+pub const DISCLAIMER: &str =
+    "These benchmarks are fictional estimates, not measurements of real users.";
+
+/// A statistic with its guessed distribution among users.
+pub struct Benchmark {
+    /// how a value is described, with `{}` standing for it, like `run {} commands/day`
+    pub unit: &'static str,
+    /// values at the 10th, 25th, 50th, 75th and 90th percentile
+    pub quantiles: [f64; 5],
+}
+
+const PERCENTILES: [f64; 5] = [10.0, 25.0, 50.0, 75.0, 90.0];
+
+pub const COMMANDS_PER_DAY: Benchmark = Benchmark {
+    unit: "run {} commands/day",
+    quantiles: [5.0, 15.0, 40.0, 90.0, 180.0],
+};
+
+pub const COMMITS_PER_WEEK: Benchmark = Benchmark {
+    unit: "make {} commits/week",
+    quantiles: [1.0, 4.0, 10.0, 25.0, 50.0],
+};
+
+pub const DISTINCT_COMMANDS: Benchmark = Benchmark {
+    unit: "have used {} distinct commands",
+    quantiles: [20.0, 40.0, 80.0, 150.0, 250.0],
+};
+
+pub const SESSIONS_PER_DAY: Benchmark = Benchmark {
+    unit: "start {} sessions/day",
+    quantiles: [0.3, 0.8, 1.5, 3.0, 5.0],
+};
+
+/// What most people run most often.
+pub const TYPICAL_TOP_COMMANDS: &[&str] = &["ls", "cd", "git"];
+
+impl Benchmark {
+    /// The estimated share of users with a lower value, in percent, interpolated between the
+    /// quantiles. Beyond the 90th percentile it approaches but never reaches 100.
+    pub fn percentile(&self, value: f64) -> f64 {
+        let q = &self.quantiles;
+        if value <= q[0] {
+            return PERCENTILES[0] * (value / q[0]).max(0.0);
+        }
+        if value >= q[4] {
+            return 100.0 - (100.0 - PERCENTILES[4]) * q[4] / value;
+        }
+        let i = q.iter().rposition(|x| *x <= value).unwrap_or(0);
+        let t = (value - q[i]) / (q[i + 1] - q[i]);
+        PERCENTILES[i] + t * (PERCENTILES[i + 1] - PERCENTILES[i])
+    }
+
+    /// `value` in the benchmark's unit, formatted with `value`.
+    pub fn describe(&self, value: impl std::fmt::Display) -> String {
+        self.unit.replace("{}", &value.to_string())
+    }
+}
+
+/// Where a percentile puts you, like `top 20%` or `bottom 30%`.
+pub fn rank_label(percentile: f64) -> String {
+    if percentile >= 50.0 {
+        format!("top {:.0}%", (100.0 - percentile).max(1.0))
+    } else {
+        format!("bottom {:.0}%", percentile.max(1.0))
+    }
+}
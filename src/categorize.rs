@@ -0,0 +1,22 @@
+//! Command categories from the `[categories]` table of the config, like
+//! `networking = ["curl", "ssh"]`, to show how the time at the terminal is spent.
+
+use std::collections::HashMap;
+
+/// The categories of each command. A command may be in several categories.
+#[derive(Debug, Default)]
+pub struct Categories(HashMap<String, Vec<String>>);
+
+impl Categories {
+    pub fn add(&mut self, category: &str, command: &str) {
+        let categories = self.0.entry(command.to_owned()).or_default();
+        if !categories.iter().any(|x| x == category) {
+            categories.push(category.to_owned());
+        }
+    }
+
+    /// The categories `command` is in, empty if it was not categorized.
+    pub fn of(&self, command: &str) -> &[String] {
+        self.0.get(command).map_or(&[], Vec::as_slice)
+    }
+}
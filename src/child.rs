@@ -0,0 +1,69 @@
+//! Reading the output of helper programs like `gzip` and `sqlite3`.
+
+use std::{
+    io::{self, Read},
+    process::{Child, ChildStdout, Command, ExitStatus, Stdio},
+};
+
+/// The standard output of a running program. A program that fails is reported as an error
+/// after its output, and one that is dropped before it finishes is killed.
+pub struct Output {
+    program: &'static str,
+    child: Child,
+    stdout: ChildStdout,
+    status: Option<ExitStatus>,
+}
+
+impl Output {
+    /// Starts `command`, which runs `program`. A missing program is reported as needed for `what`.
+    pub fn spawn(mut command: Command, program: &'static str, what: &str) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    io::Error::new(e.kind(), format!("{program} is needed to read {what}"))
+                }
+                _ => e,
+            })?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Self {
+            program,
+            child,
+            stdout,
+            status: None,
+        })
+    }
+}
+
+impl Read for Output {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.status.is_some() {
+            return Ok(0);
+        }
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            // a failure is only noticed once the program exits
+            let status = self.child.wait()?;
+            self.status = Some(status);
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "{} exited with {status}",
+                    self.program
+                )));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            // the rest of the output is not needed
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
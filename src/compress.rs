@@ -0,0 +1,58 @@
+//! Reading compressed history files. They are decompressed by running `gzip` or `zstd`, which
+//! have to be installed.
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+    process::Command,
+};
+
+use crate::child::Output;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Recognises a compressed file by its magic bytes or its extension, `None` if it is not
+    /// compressed.
+    pub fn detect(path: &Path) -> io::Result<Option<Self>> {
+        let mut magic = Vec::new();
+        File::open(path)?.take(4).read_to_end(&mut magic)?;
+        let extension = path.extension().and_then(|x| x.to_str());
+        Ok(
+            if magic.starts_with(&[0x1f, 0x8b]) || extension == Some("gz") {
+                Some(Self::Gzip)
+            } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) || extension == Some("zst") {
+                Some(Self::Zstd)
+            } else {
+                None
+            },
+        )
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// The decompressed contents of `path`.
+    pub fn open(self, path: &Path) -> io::Result<Output> {
+        let mut command = Command::new(self.program());
+        command.args(["-d", "-c", "--"]).arg(path);
+        Output::spawn(command, self.program(), &path.display().to_string())
+    }
+}
+
+/// Opens `path` for reading, decompressing it if it is compressed.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    Ok(match Compression::detect(path)? {
+        Some(compression) => Box::new(compression.open(path)?),
+        None => Box::new(File::open(path)?),
+    })
+}
@@ -0,0 +1,356 @@
+//! User configuration from `~/.config/linux_wrapped/config.toml`.
+//!
+//! Only the subset of TOML the config needs is understood: `[table]` headers, `key = value` pairs with
+//! string, integer, boolean and (possibly multi-line) array values, and `#` comments.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{
+    alias::AliasMap, categorize::Categories, filter::PrivacyFilter, ColorChoice, RECENCY_DECAY,
+    SESSION_GAP,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+/// Top-level keys are stored under the table name `""`.
+pub type Document = HashMap<String, HashMap<String, Value>>;
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    /// Skips whitespace, and with `newlines` also line breaks and comments.
+    fn skip_ws(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'\n' if newlines => self.pos += 1,
+                b'#' if newlines => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", c as char))
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(b'"' | b'\'') => self.string(),
+            _ => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_' || c == b'-')
+                {
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err("expected a key".into());
+                }
+                Ok(String::from_utf8_lossy(&self.s[start..self.pos]).into_owned())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().ok_or("expected a string")?;
+        self.pos += 1;
+        let mut result = Vec::new();
+        loop {
+            let c = self.peek().filter(|c| *c != b'\n');
+            let c = c.ok_or("unterminated string")?;
+            self.pos += 1;
+            match c {
+                c if c == quote => break,
+                b'\\' if quote == b'"' => {
+                    let escaped = self.peek().ok_or("unterminated string")?;
+                    self.pos += 1;
+                    result.push(match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'"' => b'"',
+                        b'\\' => b'\\',
+                        c => return Err(format!("unsupported escape `\\{}`", c as char)),
+                    });
+                }
+                c => result.push(c),
+            }
+        }
+        String::from_utf8(result).map_err(|_| "string is not valid UTF-8".into())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some(b'"' | b'\'') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_ws(true);
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_ws(true);
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {}
+                        _ => return Err("expected `,` or `]` in array".into()),
+                    }
+                }
+                Ok(Value::Array(items))
+            }
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| {
+                    c.is_ascii_alphanumeric() || c == b'-' || c == b'+' || c == b'_'
+                }) {
+                    self.pos += 1;
+                }
+                match &self.s[start..self.pos] {
+                    b"true" => Ok(Value::Boolean(true)),
+                    b"false" => Ok(Value::Boolean(false)),
+                    x => std::str::from_utf8(x)
+                        .ok()
+                        .and_then(|x| x.replace('_', "").parse().ok())
+                        .map(Value::Integer)
+                        .ok_or_else(|| "expected a value".into()),
+                }
+            }
+        }
+    }
+
+    fn document(&mut self) -> Result<Document, String> {
+        let mut document = Document::new();
+        let mut table = String::new();
+        loop {
+            self.skip_ws(true);
+            let Some(c) = self.peek() else {
+                return Ok(document);
+            };
+            if c == b'[' {
+                self.pos += 1;
+                self.skip_ws(false);
+                table = self.key()?;
+                self.skip_ws(false);
+                self.expect(b']')?;
+                document.entry(table.clone()).or_default();
+            } else {
+                let key = self.key()?;
+                self.skip_ws(false);
+                self.expect(b'=')?;
+                self.skip_ws(false);
+                let value = self.value()?;
+                document
+                    .entry(table.clone())
+                    .or_default()
+                    .insert(key, value);
+            }
+            self.skip_ws(false);
+            match self.peek() {
+                None | Some(b'\n' | b'#') => {}
+                _ => return Err("expected a line break".into()),
+            }
+        }
+    }
+
+    fn line(&self) -> usize {
+        self.s[..self.pos].iter().filter(|x| **x == b'\n').count() + 1
+    }
+}
+
+pub fn parse_toml(text: &str) -> Result<Document, String> {
+    let mut parser = Parser {
+        s: text.as_bytes(),
+        pos: 0,
+    };
+    parser
+        .document()
+        .map_err(|e| format!("line {}: {e}", parser.line()))
+}
+
+#[derive(Default)]
+pub struct Config {
+    /// commands that are replaced by their expansion before being counted, e.g. `gc = "git commit"`
+    pub aliases: HashMap<String, String>,
+    /// additional shell aliases for git subcommands, e.g. `gpf = "push"`
+    pub git_aliases: HashMap<String, String>,
+    /// shell aliases for cargo subcommands, e.g. `cb = "build"`
+    pub cargo_aliases: HashMap<String, String>,
+    /// commands that get their own section in the report
+    pub interesting: Vec<String>,
+    /// categories of commands, from arrays like `networking = ["curl", "ssh"]`
+    pub categories: Categories,
+    pub top_n: Option<usize>,
+    pub color: Option<ColorChoice>,
+    /// aliases from the shell rc files, expanded after the ones above
+    pub shell_aliases: AliasMap,
+    /// seconds without a command after which a new session starts, from `--session-gap`
+    pub session_gap: Option<u64>,
+    /// decay per day of the recency ranking, from `--recency-decay`
+    pub recency_decay: Option<f64>,
+    /// commands to leave out, from `--privacy-filter` and `--exclude-secrets`
+    pub privacy_filter: PrivacyFilter,
+}
+
+impl Config {
+    pub fn session_gap(&self) -> u64 {
+        self.session_gap.unwrap_or(SESSION_GAP)
+    }
+
+    pub fn recency_decay(&self) -> f64 {
+        self.recency_decay.unwrap_or(RECENCY_DECAY)
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        let mut path = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home::home_dir()?.join(".config"),
+        };
+        path.extend(["linux_wrapped", "config.toml"]);
+        Some(path)
+    }
+
+    /// Loads the config file, falling back to the defaults if there is none. Problems with the file
+    /// are reported as warnings on stderr rather than aborting.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        let (config, warnings) = match parse_toml(&text) {
+            Ok(document) => Self::from_document(document),
+            Err(e) => (Self::default(), vec![e]),
+        };
+        for warning in warnings {
+            eprintln!("linux_wrapped: warning: {}: {warning}", path.display());
+        }
+        config
+    }
+
+    pub fn from_document(mut document: Document) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut warnings = Vec::new();
+        let mismatch = |key: &str, expected: &str, value: &Value| {
+            format!("`{key}` should be {expected}, not {}", value.type_name())
+        };
+
+        for (key, value) in document.remove("").unwrap_or_default() {
+            match (key.as_str(), value) {
+                ("top_n", Value::Integer(n)) if n >= 1 => config.top_n = Some(n as usize),
+                ("color", Value::String(choice)) if ColorChoice::parse(&choice).is_some() => {
+                    config.color = ColorChoice::parse(&choice)
+                }
+                ("interesting", Value::Array(items))
+                    if items.iter().all(|x| matches!(x, Value::String(_))) =>
+                {
+                    config.interesting = items
+                        .into_iter()
+                        .filter_map(|x| match x {
+                            Value::String(x) => Some(x),
+                            _ => None,
+                        })
+                        .collect();
+                }
+                ("top_n", value) => {
+                    warnings.push(mismatch("top_n", "an integer of at least 1", &value))
+                }
+                ("color", value) => warnings.push(mismatch(
+                    "color",
+                    "one of \"auto\", \"always\", \"never\"",
+                    &value,
+                )),
+                ("interesting", value) => {
+                    warnings.push(mismatch("interesting", "an array of strings", &value))
+                }
+                (key, _) => warnings.push(format!("unknown key `{key}`")),
+            }
+        }
+        for (key, value) in document.remove("aliases").unwrap_or_default() {
+            match value {
+                Value::String(expansion) => {
+                    config.aliases.insert(key, expansion);
+                }
+                value => warnings.push(mismatch(&format!("aliases.{key}"), "a string", &value)),
+            }
+        }
+        for (key, value) in document.remove("git_aliases").unwrap_or_default() {
+            match value {
+                Value::String(subcommand) => {
+                    config.git_aliases.insert(key, subcommand);
+                }
+                value => warnings.push(mismatch(&format!("git_aliases.{key}"), "a string", &value)),
+            }
+        }
+        for (key, value) in document.remove("cargo_aliases").unwrap_or_default() {
+            match value {
+                Value::String(subcommand) => {
+                    config.cargo_aliases.insert(key, subcommand);
+                }
+                value => warnings.push(mismatch(
+                    &format!("cargo_aliases.{key}"),
+                    "a string",
+                    &value,
+                )),
+            }
+        }
+        for (key, value) in document.remove("categories").unwrap_or_default() {
+            match value {
+                Value::Array(items) if items.iter().all(|x| matches!(x, Value::String(_))) => {
+                    for item in items {
+                        if let Value::String(command) = item {
+                            config.categories.add(&key, &command);
+                        }
+                    }
+                }
+                value => warnings.push(mismatch(
+                    &format!("categories.{key}"),
+                    "an array of strings",
+                    &value,
+                )),
+            }
+        }
+        for table in document.keys() {
+            warnings.push(format!("unknown table `[{table}]`"));
+        }
+        (config, warnings)
+    }
+}
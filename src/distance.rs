@@ -0,0 +1,260 @@
+//! Finding commands that were probably typos of well-known ones.
+
+/// Commands common enough that a name one edit away from them is more likely a typo than a
+/// program of its own.
+pub const COMMON_COMMANDS: &[&str] = &[
+    "alias",
+    "apt",
+    "apt-get",
+    "awk",
+    "base64",
+    "basename",
+    "bash",
+    "bat",
+    "bc",
+    "bg",
+    "brew",
+    "bunzip2",
+    "bzip2",
+    "cal",
+    "cargo",
+    "cat",
+    "cd",
+    "chgrp",
+    "chmod",
+    "chown",
+    "chroot",
+    "clang",
+    "clear",
+    "cmake",
+    "cmp",
+    "code",
+    "column",
+    "comm",
+    "cp",
+    "cron",
+    "crontab",
+    "curl",
+    "cut",
+    "date",
+    "dd",
+    "df",
+    "diff",
+    "dig",
+    "dirname",
+    "dmesg",
+    "dnf",
+    "docker",
+    "du",
+    "echo",
+    "emacs",
+    "env",
+    "exit",
+    "export",
+    "expr",
+    "false",
+    "fd",
+    "fg",
+    "file",
+    "find",
+    "fish",
+    "fmt",
+    "fold",
+    "free",
+    "fzf",
+    "gcc",
+    "gdb",
+    "git",
+    "go",
+    "gpg",
+    "grep",
+    "groups",
+    "gunzip",
+    "gzip",
+    "head",
+    "helm",
+    "help",
+    "history",
+    "host",
+    "hostname",
+    "htop",
+    "id",
+    "ifconfig",
+    "install",
+    "ip",
+    "java",
+    "jobs",
+    "join",
+    "journalctl",
+    "jq",
+    "kill",
+    "killall",
+    "kubectl",
+    "less",
+    "ln",
+    "locate",
+    "login",
+    "logout",
+    "ls",
+    "lsblk",
+    "lsof",
+    "lsusb",
+    "make",
+    "man",
+    "md5sum",
+    "mkdir",
+    "mktemp",
+    "more",
+    "mount",
+    "mv",
+    "nano",
+    "nc",
+    "netstat",
+    "nice",
+    "nix",
+    "nl",
+    "node",
+    "nohup",
+    "npm",
+    "npx",
+    "nslookup",
+    "nvim",
+    "od",
+    "open",
+    "passwd",
+    "paste",
+    "patch",
+    "perl",
+    "pgrep",
+    "php",
+    "ping",
+    "pip",
+    "pip3",
+    "pkill",
+    "pnpm",
+    "popd",
+    "printenv",
+    "printf",
+    "ps",
+    "pushd",
+    "pwd",
+    "python",
+    "python3",
+    "read",
+    "readlink",
+    "realpath",
+    "reboot",
+    "rename",
+    "rg",
+    "rm",
+    "rmdir",
+    "rsync",
+    "ruby",
+    "rustc",
+    "rustup",
+    "scp",
+    "screen",
+    "sed",
+    "seq",
+    "service",
+    "set",
+    "sftp",
+    "sh",
+    "sha256sum",
+    "shutdown",
+    "sleep",
+    "sort",
+    "source",
+    "split",
+    "ssh",
+    "ssh-add",
+    "ssh-keygen",
+    "stat",
+    "strace",
+    "su",
+    "sudo",
+    "sync",
+    "systemctl",
+    "tac",
+    "tail",
+    "tar",
+    "tee",
+    "telnet",
+    "terraform",
+    "test",
+    "time",
+    "timeout",
+    "tmux",
+    "top",
+    "touch",
+    "tr",
+    "traceroute",
+    "tree",
+    "true",
+    "type",
+    "umount",
+    "uname",
+    "uniq",
+    "unset",
+    "unzip",
+    "uptime",
+    "useradd",
+    "usermod",
+    "vi",
+    "vim",
+    "watch",
+    "wc",
+    "wget",
+    "whereis",
+    "which",
+    "who",
+    "whoami",
+    "xargs",
+    "xclip",
+    "yarn",
+    "yes",
+    "zip",
+    "zsh",
+];
+
+/// The number of single-character insertions, deletions and substitutions needed to turn `a`
+/// into `b`.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances from a prefix of `a` to every prefix of `b`, one row at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(x != *y);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Whether `b` is `a` with two neighbouring characters swapped, like `gti` and `git`. That is two
+/// edits for [`levenshtein`], but the most common typo of all.
+pub fn is_transposition(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() != b.len() {
+        return false;
+    }
+    let differences: Vec<_> = (0..a.len()).filter(|i| a[*i] != b[*i]).collect();
+    matches!(differences[..], [i, j] if j == i + 1 && a[i] == b[j] && a[j] == b[i])
+}
+
+/// The well-known command `command` is one edit or one swap away from, unless it is well-known
+/// itself. Names shorter than three characters are too close to too many commands to tell.
+pub fn intended_command(command: &str) -> Option<&'static str> {
+    if command.chars().count() < 3 || COMMON_COMMANDS.contains(&command) {
+        return None;
+    }
+    COMMON_COMMANDS
+        .iter()
+        .find(|x| levenshtein(command, x) == 1 || is_transposition(command, x))
+        .copied()
+}
@@ -0,0 +1,115 @@
+//! A cleaned-up copy of the histories for `--export-history`, to bootstrap the history of a new
+//! machine with.
+//!
+//! Every command line is kept once, with the `VAR=value` assignments in front of it dropped and
+//! aliases in its first word expanded. The rest of the line is left exactly as it was typed.
+
+use std::{collections::HashMap, io, path::Path};
+
+use crate::{alias, config::Config, is_env_assignment, tokenize, HistoryEntry};
+
+/// Splits a command line into its first word as written, quotes included, and the rest after the
+/// whitespace following it.
+fn split_first_word(line: &[u8]) -> (&[u8], &[u8]) {
+    let mut quote = None;
+    let mut i = 0;
+    while let Some(&c) = line.get(i) {
+        match (quote, c) {
+            (None, b' ' | b'\t' | b'\n') => break,
+            (None, b'\'' | b'"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            // a backslash escapes the next character, except in single quotes
+            (None | Some(b'"'), b'\\') => i += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    let i = i.min(line.len());
+    (&line[..i], line[i..].trim_ascii_start())
+}
+
+/// `a` and `b` separated by a space, or just one of them if the other is empty.
+fn join(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = a.to_vec();
+    if !a.is_empty() && !b.is_empty() {
+        result.push(b' ');
+    }
+    result.extend(b);
+    result
+}
+
+/// The command line without leading variable assignments and with aliases in the first word
+/// expanded, first those from the config and then the shell ones.
+pub fn clean_command(line: &[u8], config: &Config) -> Vec<u8> {
+    let mut line = line.trim_ascii();
+    loop {
+        let (word, rest) = split_first_word(line);
+        if word.is_empty() || !tokenize(word).first().is_some_and(|x| is_env_assignment(x)) {
+            break;
+        }
+        line = rest;
+    }
+    let (first, rest) = split_first_word(line);
+    let (mut first, mut rest) = (first.to_vec(), rest.to_vec());
+    // like in the shell, an alias is not expanded again inside its own expansion
+    let mut expanded = Vec::new();
+    for _ in 0..alias::MAX_DEPTH {
+        let Ok(name) = std::str::from_utf8(&first) else {
+            break;
+        };
+        if expanded.iter().any(|x| x == name) {
+            break;
+        }
+        let expansion = config.aliases.get(name).filter(|_| expanded.is_empty());
+        let expansion = expansion.map(String::as_str);
+        let Some(expansion) = expansion.or_else(|| config.shell_aliases.get(name)) else {
+            break;
+        };
+        expanded.push(name.to_owned());
+        let (head, tail) = split_first_word(expansion.as_bytes());
+        rest = join(tail, &rest);
+        first = head.to_vec();
+    }
+    join(&first, &rest)
+}
+
+/// The cleaned command lines, each once with the time it was last run, most recent first.
+/// Entries without a timestamp count as run at 0.
+pub fn deduplicate(
+    entries: impl IntoIterator<Item = HistoryEntry>,
+    config: &Config,
+) -> Vec<(Vec<u8>, u64)> {
+    let mut last_seen: HashMap<Vec<u8>, u64> = HashMap::new();
+    for entry in entries {
+        if config.privacy_filter.matches(&entry.command) {
+            continue;
+        }
+        let command = clean_command(&entry.command, config);
+        if command.is_empty() {
+            continue;
+        }
+        let timestamp = entry.timestamp.unwrap_or(0);
+        let seen = last_seen.entry(command).or_default();
+        *seen = timestamp.max(*seen);
+    }
+    let mut entries: Vec<_> = last_seen.into_iter().collect();
+    entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    entries
+}
+
+/// Writes the entries as a zsh history in the extended format, `: <timestamp>:0;<command>`.
+/// Line breaks in commands are escaped with a backslash, as zsh does.
+pub fn write_zsh_history(entries: &[(Vec<u8>, u64)], path: &Path) -> io::Result<()> {
+    let mut out = Vec::new();
+    for (command, timestamp) in entries {
+        out.extend(format!(": {timestamp}:0;").as_bytes());
+        for &c in command {
+            if c == b'\n' {
+                out.push(b'\\');
+            }
+            out.push(c);
+        }
+        out.push(b'\n');
+    }
+    std::fs::write(path, out)
+}
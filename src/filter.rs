@@ -0,0 +1,118 @@
+//! The privacy filter of `--privacy-filter` and `--exclude-secrets`, which drops commands matching
+//! glob patterns before anything is counted.
+//!
+//! Patterns match the whole command line: `*` stands for any text, `?` for one character, and
+//! `[...]` for one of a set of characters like `[a-z]`, or with `[!...]` one not in it.
+
+/// Patterns for commands that handle secrets or carry them in the command line.
+pub const SECRET_PATTERNS: &[&str] = &[
+    "pass",
+    "pass *",
+    "gopass *",
+    "gpg *",
+    "secret-tool *",
+    "op *",
+    "*PASSWORD*",
+    "*password*",
+    "*SECRET*",
+    "*secret*",
+    "*TOKEN=*",
+    "*_KEY=*",
+    "*Authorization:*",
+    "*ghp_*",
+    "*github_pat_*",
+    "*AKIA[0-9A-Z][0-9A-Z]*",
+    "*xox[abprs]-*",
+];
+
+#[derive(Debug, Default)]
+pub struct PrivacyFilter {
+    patterns: Vec<Vec<u8>>,
+}
+
+impl PrivacyFilter {
+    pub fn add(&mut self, pattern: &str) {
+        self.patterns.push(pattern.as_bytes().to_vec());
+    }
+
+    /// Adds the [`SECRET_PATTERNS`].
+    pub fn exclude_secrets(&mut self) {
+        for pattern in SECRET_PATTERNS {
+            self.add(pattern);
+        }
+    }
+
+    /// Whether the command line should be left out.
+    pub fn matches(&self, command: &[u8]) -> bool {
+        let command = command.trim_ascii();
+        self.patterns.iter().any(|x| glob_match(x, command))
+    }
+}
+
+/// The length of the `[...]` set at the start of `pattern` and whether it contains `c`, `None` if
+/// it is not closed, in which case the `[` is an ordinary character.
+fn match_set(pattern: &[u8], c: u8) -> Option<(usize, bool)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some(b'!' | b'^'));
+    if negated {
+        i += 1;
+    }
+    let mut found = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        // a `]` right after the opening bracket is part of the set
+        if start == b']' && !first {
+            return Some((i + 1, found != negated));
+        }
+        first = false;
+        match (pattern.get(i + 1), pattern.get(i + 2)) {
+            (Some(b'-'), Some(&end)) if end != b']' => {
+                found |= (start..=end).contains(&c);
+                i += 3;
+            }
+            _ => {
+                found |= start == c;
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Whether `text` as a whole matches the glob `pattern`.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // where to resume after the last `*` if the rest does not match: pattern after it, and text
+    let mut star = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(1),
+            Some(b'[') => match match_set(&pattern[p..], text[t]) {
+                Some((len, true)) => Some(len),
+                Some((_, false)) => None,
+                None => (text[t] == b'[').then_some(1),
+            },
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+        match (step, star) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            (None, Some((after, start))) => {
+                // let the `*` take one more character
+                p = after;
+                t = start + 1;
+                star = Some((after, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|x| *x == b'*')
+}
@@ -0,0 +1,73 @@
+//! Charts of activity over time that fit in a terminal.
+
+use std::fmt::Write;
+
+use crate::time::{Date, MONTHS};
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// One block character per count, scaled to the largest of them.
+pub fn sparkline(counts: &[u64]) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|x| SPARKS[(x * (SPARKS.len() as u64 - 1)).div_ceil(max) as usize])
+        .collect()
+}
+
+fn date_label(date: Date, with_year: bool) -> String {
+    let month = MONTHS[usize::from(date.month) - 1];
+    if with_year {
+        format!("{month} {}", date.year)
+    } else {
+        format!("{month} {}", date.day)
+    }
+}
+
+/// A bar chart of the commands per day, `height` rows of `#` high and at most `width` columns
+/// wide, with the largest count on the y-axis and the first and last date below the x-axis. If
+/// there are more days than columns, neighbouring days are added up into one column. Plain ASCII
+/// only, so it works in any terminal and font.
+pub fn render_ascii_graph(daily_counts: &[(Date, u64)], width: usize, height: usize) -> String {
+    let mut out = String::new();
+    if daily_counts.is_empty() || width == 0 || height == 0 {
+        return out;
+    }
+    let days_per_column = daily_counts.len().div_ceil(width);
+    let columns: Vec<(Date, u64)> = daily_counts
+        .chunks(days_per_column)
+        .map(|x| (x[0].0, x.iter().map(|x| x.1).sum()))
+        .collect();
+    let max = columns.iter().map(|x| x.1).max().unwrap_or(0).max(1);
+    let axis = max.to_string().len();
+    for row in (1..=height as u64).rev() {
+        let label = if row == height as u64 {
+            max.to_string()
+        } else {
+            String::new()
+        };
+        let mut line = format!("{label:>axis$} |");
+        for (_, count) in &columns {
+            // filled if the bar reaches above the bottom edge of this row
+            let filled = count * height as u64 > (row - 1) * max;
+            line.push(if filled { '#' } else { ' ' });
+        }
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+    writeln!(out, "{:>axis$} +{}", 0, "-".repeat(columns.len())).unwrap();
+
+    let (first, last) = (daily_counts[0].0, daily_counts[daily_counts.len() - 1].0);
+    // long ranges are labelled by month, short ones by day
+    let with_year = daily_counts.len() > 180;
+    let (first, last) = (date_label(first, with_year), date_label(last, with_year));
+    let gap = columns.len().saturating_sub(first.len() + last.len());
+    if gap > 0 {
+        writeln!(out, "{:axis$}  {first}{}{last}", "", " ".repeat(gap)).unwrap();
+    } else {
+        writeln!(out, "{:axis$}  {first}", "").unwrap();
+    }
+    if days_per_column > 1 {
+        writeln!(out, "(each column is {days_per_column} days)").unwrap();
+    }
+    out
+}
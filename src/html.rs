@@ -0,0 +1,103 @@
+//! Self-contained HTML report for `--html`, with bar charts drawn as inline SVG.
+
+use std::fmt::Write;
+
+use linux_wrapped::{anonymize, man_page_label, most_common, State};
+
+use crate::DisplayConfig;
+
+const ROW_HEIGHT: usize = 24;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Horizontal bar chart, each bar scaled to the largest count in `entries`.
+fn bar_chart(out: &mut String, title: &str, entries: &[(u64, String)]) {
+    let Some(max) = entries.iter().map(|x| x.0).max().filter(|x| *x > 0) else {
+        return;
+    };
+    writeln!(out, "<section><h2>{}</h2>", escape(title)).unwrap();
+    writeln!(
+        out,
+        r#"<svg width="100%" height="{}" role="img">"#,
+        entries.len() * ROW_HEIGHT
+    )
+    .unwrap();
+    for (i, (count, label)) in entries.iter().enumerate() {
+        let y = i * ROW_HEIGHT;
+        let width = *count as f64 / max as f64 * 100.0;
+        writeln!(
+            out,
+            r#"<text x="0" y="{}" class="label">{}</text><svg x="25%" width="65%"><rect y="{}" width="{width:.2}%" height="{}" rx="3"/></svg><text x="91%" y="{}" class="count">{count}</text>"#,
+            y + 16,
+            escape(label),
+            y + 4,
+            ROW_HEIGHT - 8,
+            y + 16,
+        )
+        .unwrap();
+    }
+    out.push_str("</svg></section>\n");
+}
+
+fn top(map: &std::collections::HashMap<String, u32>, n: usize) -> Vec<(u64, String)> {
+    most_common(map)
+        .into_iter()
+        .take(n)
+        .map(|(count, name)| (count as u64, name.clone()))
+        .collect()
+}
+
+/// With `anonymize`, host names, paths and the like in the commands are replaced by placeholders.
+pub fn render(state: &State, display: DisplayConfig, anonymize: bool) -> String {
+    let mut out = String::from(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>linux_wrapped</title>
+<style>
+body { font-family: system-ui, sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; background: #16161d; color: #e6e6e6; }
+h1 { color: #f4bf4f; }
+h2 { color: #7fd1b9; font-size: 1.2em; }
+rect { fill: #7fd1b9; }
+text { fill: #e6e6e6; font-size: 14px; }
+.label { font-family: ui-monospace, monospace; }
+</style>
+</head>
+<body>
+<h1>Your linux_wrapped</h1>
+"#,
+    );
+    let mut commands = top(&state.commands, display.commands);
+    if anonymize {
+        for (_, command) in &mut commands {
+            *command = anonymize::anonymize(command);
+        }
+    }
+    bar_chart(&mut out, "Top commands", &commands);
+    bar_chart(
+        &mut out,
+        "Git subcommands",
+        &top(&state.git_subcommands, display.git),
+    );
+    let man_pages: Vec<_> = most_common(&state.man_pages)
+        .into_iter()
+        .take(display.man)
+        .map(|(count, (section, page))| (count as u64, man_page_label(*section, page)))
+        .collect();
+    bar_chart(&mut out, "Man pages", &man_pages);
+    let hours: Vec<_> = state
+        .hourly_counts
+        .iter()
+        .enumerate()
+        .map(|(hour, count)| (*count, format!("{hour:02}:00")))
+        .collect();
+    bar_chart(&mut out, "Commands by hour of day", &hours);
+    out.push_str("</body>\n</html>\n");
+    out
+}
@@ -0,0 +1,4127 @@
+//! Statistics about shell history, as reported by the `linux_wrapped` binary.
+//!
+//! Histories are read as [`CommandHistory`] iterators of [`HistoryEntry`], for example from a
+//! [`ZshHistory`] or any shell's as a [`History`], and counted into a [`State`] by
+//! [`process_command_history`]. The states of several histories can be combined with
+//! [`merge_states`].
+
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Split},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use analyzer::Analyzer;
+use compress::Compression;
+use config::Config;
+use json::ToJson;
+use mmap::Mmap;
+use persist::{Fields, Persist};
+
+pub mod alias;
+pub mod analyzer;
+pub mod anonymize;
+pub mod benchmarks;
+pub mod categorize;
+mod child;
+pub mod compress;
+pub mod config;
+pub mod distance;
+pub mod export;
+pub mod filter;
+pub mod graph;
+pub mod json;
+mod mmap;
+pub mod parallel;
+pub mod persist;
+pub mod stats;
+pub mod template;
+pub mod time;
+
+/// A command line from a history file.
+pub struct HistoryEntry {
+    pub command: Vec<u8>,
+    /// unix timestamp of when the command was started, if the history format records it
+    pub timestamp: Option<u64>,
+    /// how long the command ran, if the history format records it
+    pub elapsed: Option<Duration>,
+    /// the exit status of the command, if the history format records it
+    pub exit_status: Option<i32>,
+}
+
+/// A parsed history file, or any other source of [`HistoryEntry`]s.
+pub trait CommandHistory: Iterator<Item = HistoryEntry> + Send {}
+
+impl<T: Iterator<Item = HistoryEntry> + Send> CommandHistory for T {}
+
+/// The history of any supported shell, so the shells can be told apart without boxing them.
+pub enum History {
+    Zsh(ZshHistory),
+    Bash(BashHistory),
+    Fish(FishHistory),
+    Nushell(NushellHistory),
+    Tcsh(TcshHistory),
+    Xonsh(XonshHistory),
+    #[cfg(any(test, feature = "testing"))]
+    Mock(MockHistory),
+}
+
+impl Iterator for History {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<HistoryEntry> {
+        match self {
+            Self::Zsh(x) => x.next(),
+            Self::Bash(x) => x.next(),
+            Self::Fish(x) => x.next(),
+            Self::Nushell(x) => x.next(),
+            Self::Tcsh(x) => x.next(),
+            Self::Xonsh(x) => x.next(),
+            #[cfg(any(test, feature = "testing"))]
+            Self::Mock(x) => x.next(),
+        }
+    }
+}
+
+/// A history of the given command lines, without timestamps or durations, for tests. Outside of
+/// this crate's own tests it needs the `testing` feature.
+#[cfg(any(test, feature = "testing"))]
+pub struct MockHistory(std::vec::IntoIter<Vec<u8>>);
+
+#[cfg(any(test, feature = "testing"))]
+impl MockHistory {
+    pub fn new(lines: Vec<Vec<u8>>) -> Self {
+        Self(lines.into_iter())
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Iterator for MockHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<HistoryEntry> {
+        Some(HistoryEntry {
+            command: self.0.next()?,
+            timestamp: None,
+            elapsed: None,
+            exit_status: None,
+        })
+    }
+}
+
+/// Reads a history file line by line, see [`compress::open`].
+type Lines = Split<BufReader<Box<dyn Read + Send>>>;
+
+fn open_lines(path: &Path) -> Option<Lines> {
+    let reader = compress::open(path).ok()?;
+    Some(BufReader::new(reader).split(b'\n'))
+}
+
+fn parse_u64(x: &[u8]) -> Option<u64> {
+    std::str::from_utf8(x).ok()?.trim().parse().ok()
+}
+
+/// Where the lines of a [`ZshHistory`] come from.
+enum ZshLines {
+    Buffered(Peekable<Lines>),
+    /// the whole file, and the offset of the next line in it
+    Mapped(Mmap, usize),
+}
+
+impl ZshLines {
+    /// The next line without its line break. Mapped lines are borrowed from the mapping.
+    fn next(&mut self) -> Option<std::io::Result<Cow<'_, [u8]>>> {
+        match self {
+            Self::Buffered(lines) => lines.next().map(|x| x.map(Cow::Owned)),
+            Self::Mapped(map, pos) => {
+                let rest = map.get(*pos..).filter(|x| !x.is_empty())?;
+                let line = rest.split(|x| *x == b'\n').next().unwrap_or_default();
+                *pos += line.len() + 1;
+                Some(Ok(Cow::Borrowed(line)))
+            }
+        }
+    }
+
+    /// Whether the next line continues the current entry instead of starting a new one.
+    fn continues(&mut self) -> bool {
+        match self {
+            Self::Buffered(lines) => {
+                matches!(lines.peek(), Some(Ok(line)) if !line.starts_with(b":"))
+            }
+            Self::Mapped(map, pos) => map.get(*pos).is_some_and(|x| *x != b':'),
+        }
+    }
+}
+
+/// The zsh history, in the extended format with timestamps and durations or the plain one.
+pub struct ZshHistory {
+    lines: ZshLines,
+    /// byte offset in the file up to which entries have been yielded
+    offset: u64,
+    len: u64,
+    /// byte offset of the first entry that belongs to someone else, see [`ZshHistory::chunks`]
+    end: u64,
+}
+
+impl ZshHistory {
+    /// `~/.zsh_history`
+    pub fn path() -> Option<PathBuf> {
+        Some(home::home_dir()?.join(".zsh_history"))
+    }
+
+    /// Opens the history and starts parsing at byte `offset`, e.g. where a previous run stopped.
+    pub fn open(path: &Path, offset: Option<u64>) -> Option<Self> {
+        let len = std::fs::metadata(path).ok()?.len();
+        // a history that got shorter was rewritten since the offset was recorded
+        let offset = offset.filter(|x| *x <= len).unwrap_or(0);
+        Self::open_range(path, offset, u64::MAX, false)
+    }
+
+    /// Opens the history, yielding only the entries that start in the byte range `start..end`.
+    /// `start` has to be the beginning of an entry, and `end` can be `u64::MAX` to read the whole
+    /// rest. The offsets of a compressed history are in its decompressed contents, which have to
+    /// be decompressed from the start in any case.
+    ///
+    /// With `mmap` the file is memory-mapped instead of read through a buffer, and commands are
+    /// copied straight out of the mapping, which saves copying the file through the kernel. A
+    /// history that is truncated while it is mapped crashes the process though, so it is opt-in.
+    pub fn open_range(path: &Path, start: u64, end: u64, mmap: bool) -> Option<Self> {
+        let mut f = File::open(path).ok()?;
+        let (lines, len) = match Compression::detect(path).ok()? {
+            Some(compression) => {
+                let mut reader: Box<dyn Read + Send> = Box::new(compression.open(path).ok()?);
+                std::io::copy(&mut reader.by_ref().take(start), &mut std::io::sink()).ok()?;
+                let lines = BufReader::new(reader).split(b'\n').peekable();
+                (ZshLines::Buffered(lines), u64::MAX)
+            }
+            None if mmap => {
+                let map = Mmap::map(&f).ok()?;
+                let len = map.len() as u64;
+                (ZshLines::Mapped(map, start.try_into().ok()?), len)
+            }
+            None => {
+                let len = f.metadata().ok()?.len();
+                f.seek(SeekFrom::Start(start)).ok()?;
+                let reader: Box<dyn Read + Send> = Box::new(f);
+                let lines = BufReader::new(reader).split(b'\n').peekable();
+                (ZshLines::Buffered(lines), len)
+            }
+        };
+        Some(Self {
+            lines,
+            offset: start,
+            len,
+            end,
+        })
+    }
+
+    /// Splits the history into up to `n` byte ranges of about the same size that each start at the
+    /// beginning of an entry, so they can be parsed independently with [`ZshHistory::open_range`].
+    /// A compressed history is a single range.
+    pub fn chunks(path: &Path, n: usize) -> std::io::Result<Vec<(u64, u64)>> {
+        if Compression::detect(path)?.is_some() {
+            return Ok(vec![(0, u64::MAX)]);
+        }
+        let mut f = BufReader::new(File::open(path)?);
+        let len = f.get_ref().metadata()?.len();
+        let mut starts = vec![0];
+        for i in 1..n as u64 {
+            let guess = len * i / n as u64;
+            if guess <= *starts.last().unwrap() {
+                continue;
+            }
+            f.seek(SeekFrom::Start(guess))?;
+            // the guess is most likely in the middle of a line, so skip to the next one
+            let mut pos = guess + f.skip_until(b'\n')? as u64;
+            let mut line = Vec::new();
+            loop {
+                let escaped = line.ends_with(b"\\\n");
+                line.clear();
+                let read = f.read_until(b'\n', &mut line)?;
+                if read == 0 || line.starts_with(b":") && !escaped {
+                    break;
+                }
+                pos += read as u64;
+            }
+            if pos < len {
+                starts.push(pos);
+            }
+        }
+        starts.dedup();
+        let ends = starts.iter().skip(1).copied().chain([len]);
+        Ok(starts.iter().copied().zip(ends).collect())
+    }
+
+    /// Where the next run should continue parsing from.
+    pub fn offset(&self) -> u64 {
+        // the last line might not end in a newline
+        self.offset.min(self.len)
+    }
+}
+
+impl Iterator for ZshHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.offset >= self.end {
+                return None;
+            }
+            let item = self.lines.next()?;
+            if let Ok(line) = &item {
+                self.offset += line.len() as u64 + 1;
+            }
+            if let Some(mut result) = item.ok().and_then(|x| {
+                if x.is_empty() || x[0] != b':' {
+                    return None;
+                }
+                // `: <start>:<elapsed>;<command>`
+                let mut it = x.splitn(2, |x| *x == b';');
+                let mut meta = it.next()?[1..].split(|x| *x == b':');
+                let timestamp = meta.next().and_then(parse_u64);
+                let elapsed = meta.next().and_then(parse_u64).map(Duration::from_secs);
+                it.next().map(|x| HistoryEntry {
+                    command: x.to_owned(),
+                    timestamp,
+                    elapsed,
+                    exit_status: None,
+                })
+            }) {
+                // zsh writes the line breaks inside a command as a backslash before the newline,
+                // and the next line continues the command even if it starts with `:`
+                loop {
+                    let escaped = result.command.ends_with(b"\\");
+                    if !escaped && !self.lines.continues() {
+                        break;
+                    }
+                    let Some(Ok(line)) = self.lines.next() else {
+                        break;
+                    };
+                    self.offset += line.len() as u64 + 1;
+                    if escaped {
+                        result.command.pop();
+                    }
+                    result.command.push(b'\n');
+                    result.command.extend(&*line);
+                }
+
+                return Some(result);
+            }
+        }
+    }
+}
+
+/// The bash history, with timestamps if `HISTTIMEFORMAT` was set when it was written.
+pub struct BashHistory {
+    lines: Lines,
+    /// timestamp from the most recent `#<unix timestamp>` line
+    timestamp: Option<u64>,
+}
+
+impl BashHistory {
+    /// `$HISTFILE`, or `~/.bash_history` if it is not set.
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os("HISTFILE") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(home::home_dir()?.join(".bash_history")),
+        }
+    }
+
+    pub fn open(path: &Path) -> Option<Self> {
+        Some(Self {
+            lines: open_lines(path)?,
+            timestamp: None,
+        })
+    }
+}
+
+impl Iterator for BashHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ok(line) = self.lines.next()? else {
+                continue;
+            };
+            // with HISTTIMEFORMAT set, bash writes a `#<unix timestamp>` line before each command
+            let is_timestamp =
+                line.len() > 1 && line[0] == b'#' && line[1..].iter().all(|x| x.is_ascii_digit());
+            if is_timestamp {
+                self.timestamp = parse_u64(&line[1..]);
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            return Some(HistoryEntry {
+                command: line,
+                timestamp: self.timestamp.take(),
+                elapsed: None,
+                exit_status: None,
+            });
+        }
+    }
+}
+
+/// The tcsh history, in which `savehist` writes a `#+<unix timestamp>` line before each command.
+/// Histories written by older versions have no timestamps.
+pub struct TcshHistory {
+    lines: Lines,
+    /// timestamp from the most recent `#+<unix timestamp>` line
+    timestamp: Option<u64>,
+}
+
+impl TcshHistory {
+    /// `~/.history`
+    pub fn path() -> Option<PathBuf> {
+        Some(home::home_dir()?.join(".history"))
+    }
+
+    pub fn open(path: &Path) -> Option<Self> {
+        Some(Self {
+            lines: open_lines(path)?,
+            timestamp: None,
+        })
+    }
+
+    fn parse_timestamp(line: &[u8]) -> Option<u64> {
+        let digits = line.strip_prefix(b"#+")?;
+        if digits.is_empty() || !digits.iter().all(|x| x.is_ascii_digit()) {
+            return None;
+        }
+        parse_u64(digits)
+    }
+}
+
+impl Iterator for TcshHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ok(line) = self.lines.next()? else {
+                continue;
+            };
+            if let Some(timestamp) = Self::parse_timestamp(&line) {
+                self.timestamp = Some(timestamp);
+                continue;
+            }
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            return Some(HistoryEntry {
+                command: line,
+                timestamp: self.timestamp.take(),
+                elapsed: None,
+                exit_status: None,
+            });
+        }
+    }
+}
+
+/// The xonsh history, which xonsh's JSON backend spreads over one file per session. All of them
+/// are read up front so the commands can be put in order.
+pub struct XonshHistory {
+    entries: std::vec::IntoIter<HistoryEntry>,
+}
+
+impl XonshHistory {
+    /// `~/.local/share/xonsh`, in which the session files are found
+    pub fn path() -> Option<PathBuf> {
+        let mut path = home::home_dir()?;
+        path.extend([".local", "share", "xonsh"]);
+        Some(path)
+    }
+
+    /// Reads a single session file, or all `xonsh-*.json` files in a directory and its
+    /// `history_json` subdirectory. `None` if there are none.
+    pub fn open(path: &Path) -> Option<Self> {
+        let files = if path.is_dir() {
+            let mut files = Vec::new();
+            for dir in [path.to_owned(), path.join("history_json")] {
+                let Ok(entries) = std::fs::read_dir(dir) else {
+                    continue;
+                };
+                files.extend(
+                    entries
+                        .filter_map(|x| x.ok())
+                        .map(|x| x.path())
+                        .filter(|x| {
+                            x.file_name()
+                                .and_then(|x| x.to_str())
+                                .is_some_and(|x| x.starts_with("xonsh-") && x.ends_with(".json"))
+                        }),
+                );
+            }
+            files
+        } else {
+            vec![path.to_owned()]
+        };
+        if files.is_empty() {
+            return None;
+        }
+        let mut entries: Vec<_> = files
+            .iter()
+            .filter_map(|x| std::fs::read(x).ok())
+            .filter_map(|x| json::parse(&x).ok())
+            .flat_map(|x| Self::commands(&x))
+            .collect();
+        // commands without a timestamp go first, where they do not interrupt sessions
+        entries.sort_by_key(|x| x.timestamp);
+        Some(Self {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// The commands of a session file, which are under `data.cmds`, or of a plain array of them.
+    fn commands(file: &json::Value) -> Vec<HistoryEntry> {
+        let commands = file
+            .get("data")
+            .and_then(|x| x.get("cmds"))
+            .unwrap_or(file)
+            .as_array()
+            .unwrap_or_default();
+        commands
+            .iter()
+            .filter_map(|x| {
+                let command = x.get("inp")?.as_str()?.trim_end();
+                // `ts` is the start and the end, or just the start
+                let (start, end) = match x.get("ts") {
+                    Some(json::Value::Array(ts)) => (
+                        ts.first().and_then(json::Value::as_f64),
+                        ts.get(1).and_then(json::Value::as_f64),
+                    ),
+                    Some(ts) => (ts.as_f64(), None),
+                    None => (None, None),
+                };
+                let start = start.filter(|x| *x >= 0.0);
+                Some(HistoryEntry {
+                    command: command.as_bytes().to_vec(),
+                    timestamp: start.map(|x| x as u64),
+                    elapsed: start
+                        .zip(end)
+                        .and_then(|(start, end)| Duration::try_from_secs_f64(end - start).ok()),
+                    exit_status: x.get("rtn").and_then(json::Value::as_f64).map(|x| x as i32),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Iterator for XonshHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+}
+
+/// The fish history, which always records timestamps.
+pub struct FishHistory {
+    lines: Peekable<Lines>,
+}
+
+impl FishHistory {
+    /// `~/.local/share/fish/fish_history`
+    pub fn path() -> Option<PathBuf> {
+        let mut path = home::home_dir()?;
+        path.extend([".local", "share", "fish", "fish_history"]);
+        Some(path)
+    }
+
+    pub fn open(path: &Path) -> Option<Self> {
+        Some(Self {
+            lines: open_lines(path)?.peekable(),
+        })
+    }
+
+    /// fish escapes newlines in multi-line commands as `\n` and backslashes as `\\`
+    fn unescape(cmd: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(cmd.len());
+        let mut it = cmd.iter();
+        while let Some(&c) = it.next() {
+            match (c, it.as_slice().first()) {
+                (b'\\', Some(b'n')) => {
+                    result.push(b'\n');
+                    it.next();
+                }
+                (b'\\', Some(b'\\')) => {
+                    result.push(b'\\');
+                    it.next();
+                }
+                _ => result.push(c),
+            }
+        }
+        result
+    }
+}
+
+impl Iterator for FishHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ok(line) = self.lines.next()? else {
+                continue;
+            };
+            let Some(cmd) = line.strip_prefix(b"- cmd: ") else {
+                continue;
+            };
+            let result = Self::unescape(cmd);
+            let mut timestamp = None;
+            // the remaining fields of the record are indented, the next record starts with `- cmd:`
+            while let Some(Ok(next)) = self.lines.peek() {
+                if !next.starts_with(b" ") {
+                    break;
+                }
+                if let Some(when) = next.trim_ascii_start().strip_prefix(b"when: ") {
+                    timestamp = parse_u64(when);
+                }
+                self.lines.next();
+            }
+            return Some(HistoryEntry {
+                command: result,
+                timestamp,
+                elapsed: None,
+                exit_status: None,
+            });
+        }
+    }
+}
+
+/// The Nushell history, when it is configured with `file_format = "sqlite"`, which records
+/// timestamps, durations and exit statuses. The database is read with the `sqlite3` program.
+pub struct NushellHistory {
+    rows: Lines,
+}
+
+impl NushellHistory {
+    /// `~/.local/share/nushell/history.sqlite3`
+    pub fn path() -> Option<PathBuf> {
+        let mut path = home::home_dir()?;
+        path.extend([".local", "share", "nushell", "history.sqlite3"]);
+        Some(path)
+    }
+
+    pub fn open(path: &Path) -> Option<Self> {
+        if !path.is_file() {
+            return None;
+        }
+        // the command is hex encoded so that commands spanning lines or containing `|` stay intact
+        let mut command = std::process::Command::new("sqlite3");
+        command.args(["-readonly", "-batch", "-noheader", "-separator", "|", "--"]);
+        command.arg(path).arg(
+            "SELECT start_timestamp, duration_ms, exit_status, hex(command_line) \
+             FROM history ORDER BY id",
+        );
+        let output = match child::Output::spawn(command, "sqlite3", &path.display().to_string()) {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("linux_wrapped: warning: {e}");
+                return None;
+            }
+        };
+        let reader: Box<dyn Read + Send> = Box::new(output);
+        Some(Self {
+            rows: BufReader::new(reader).split(b'\n'),
+        })
+    }
+
+    fn parse_hex(hex: &[u8]) -> Option<Vec<u8>> {
+        hex.chunks(2)
+            .map(|x| u8::from_str_radix(std::str::from_utf8(x).ok()?, 16).ok())
+            .collect()
+    }
+}
+
+impl Iterator for NushellHistory {
+    type Item = HistoryEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Ok(row) = self.rows.next()? else {
+                continue;
+            };
+            let mut columns = row.split(|x| *x == b'|');
+            let (Some(start), Some(duration), Some(status), Some(command), None) = (
+                columns.next(),
+                columns.next(),
+                columns.next(),
+                columns.next(),
+                columns.next(),
+            ) else {
+                continue;
+            };
+            let Some(command) = Self::parse_hex(command.trim_ascii()) else {
+                continue;
+            };
+            return Some(HistoryEntry {
+                command,
+                // Nushell stores milliseconds
+                timestamp: parse_u64(start).map(|x| x / 1000),
+                elapsed: parse_u64(duration).map(Duration::from_millis),
+                exit_status: std::str::from_utf8(status)
+                    .ok()
+                    .and_then(|x| x.trim().parse().ok()),
+            });
+        }
+    }
+}
+
+/// Everything counted from the histories, from which the report is made.
+#[derive(Default)]
+pub struct State {
+    /// keyed by section number (0 if none was given) and page
+    pub man_pages: HashMap<(u8, String), u32>,
+    pub git_subcommands: HashMap<String, u32>,
+    pub commands: HashMap<String, u32>,
+    /// total seconds spent per command
+    pub command_durations: HashMap<String, u64>,
+    /// oldest and newest entry timestamps
+    pub time_span: Option<(u64, u64)>,
+    /// number of commands started in each hour of the day (local time)
+    pub hourly_counts: [u64; 24],
+    /// number of commands started on each day of the week, starting on Monday (local time)
+    pub weekday_counts: [u64; 7],
+    /// how many entries were pipelines of each length
+    pub pipeline_depth_histogram: HashMap<u8, u64>,
+    /// how many commands were run through sudo or doas
+    pub sudo_count: u64,
+    /// start timestamps of all entries that have one
+    pub entry_timestamps: Vec<u64>,
+    /// package manager operations as `manager:subcommand`, e.g. `apt:install`
+    pub package_manager_ops: HashMap<String, u32>,
+    /// docker subcommands, with the image for `run`, `pull` and `build`
+    pub docker_ops: HashMap<(String, Option<String>), u32>,
+    /// kubectl operations as `subcommand/resource`, e.g. `get/pods` or `logs/-`
+    pub k8s_ops: HashMap<String, u32>,
+    /// hosts connected to with ssh, scp and rsync
+    pub ssh_hosts: HashMap<String, u32>,
+    /// targets of `cd`, `z` and `zoxide`, with `~` expanded
+    pub directories: HashMap<String, u32>,
+    /// number of `cd -`
+    pub previous_dir_count: u64,
+    /// files opened in an editor
+    pub editor_files: HashMap<String, u32>,
+    /// number of times each editor was started
+    pub editors: HashMap<String, u32>,
+    /// timestamp at which each command was first used
+    pub commands_first_seen: HashMap<String, u64>,
+    /// the longest command line and its length in bytes
+    pub longest_command: Option<(Vec<u8>, usize)>,
+    /// the command invoked with the most arguments, and how many
+    pub most_args_command: Option<(String, usize)>,
+    /// consecutive commands within a session, with git commands including their subcommand
+    pub command_bigrams: HashMap<(String, String), u32>,
+    /// number of command lines
+    pub total_commands: u64,
+    /// custom analyzers that see every command; only those with a name are saved and written to
+    /// JSON, see [`Analyzer::name`]
+    pub analyzers: Vec<Box<dyn Analyzer>>,
+    /// cargo subcommands, with its built-in aliases like `cargo b` resolved
+    pub cargo_subcommands: HashMap<String, u32>,
+    /// JavaScript package manager operations as `manager:subcommand`, or `manager:run:script` for scripts
+    pub npm_ops: HashMap<String, u32>,
+    /// systemd unit operations as `operation:unit`, with ` (user)` appended for `--user` units
+    pub systemctl_ops: HashMap<String, u32>,
+    /// commands that were probably mistyped as `(typo, intended, count)`, most frequent first, see [`detect_typos`]
+    pub likely_typos: Vec<(String, String, u32)>,
+    /// command lines run in the background with a trailing `&` or `nohup`
+    pub background_jobs: u64,
+    /// how often each command was run in the background
+    pub backgrounded_commands: HashMap<String, u32>,
+    /// patterns searched for with grep and friends, and the names looked for with `find` and `fd`
+    pub search_patterns: HashMap<String, u32>,
+    /// how often each search tool was used
+    pub search_tools: HashMap<String, u32>,
+    /// hosts fetched from with `curl` or `wget`
+    pub http_targets: HashMap<String, u32>,
+    /// build targets as `tool:target`, with `tool:default` when none was named, see [`build_targets`]
+    pub build_targets: HashMap<String, u32>,
+    /// undo operations like `git reset`, `git revert`, `git checkout -- .` and `rm -rf`, see [`is_undo`]
+    pub undo_count: u64,
+    /// number of `rm -rf`, `truncate` and `dd` runs, see [`is_destructive`]
+    pub destructive_commands: u64,
+    /// share of command lines that ran a destructive command
+    pub danger_ratio: f64,
+    /// number of commands per ISO week, keyed by year and week number
+    pub weekly_counts: HashMap<(i32, u8), u64>,
+    /// Python environments activated with `source <env>/bin/activate` or `conda activate`
+    pub venv_activations: HashMap<String, u32>,
+    /// commands run with `--help` or `-h`, see [`asks_for_help`]
+    pub help_sought: HashMap<String, u32>,
+    /// every command with when it was run, for [`weighted_ranking`]
+    pub command_timestamps: Vec<(String, u64)>,
+    /// uses of the [`SHELL_BUILTINS`]
+    pub builtins: HashMap<String, u32>,
+    /// cloud CLI operations as `cli:subcommand[:resource]`, see [`cloud_op`]
+    pub cloud_ops: HashMap<String, u32>,
+    /// lengths of `jq` filters, bucketed by their base-2 logarithm
+    pub jq_filter_lengths: HashMap<u8, u32>,
+    /// the longest `jq` filter
+    pub longest_jq_filter: Option<String>,
+    /// number of commands in each category from the config, see [`categorize`]
+    pub command_categories: HashMap<String, u32>,
+    /// for each command, in how many of its invocations each argument appeared, without the subcommand of tools like git
+    pub per_command_args: HashMap<String, HashMap<String, u32>>,
+    /// terraform subcommands, with the action and workspace name for `workspace`, like `workspace:select:prod`
+    pub terraform_ops: HashMap<String, u32>,
+    /// how often each word was typed as an argument, leaving out flags and paths
+    pub argument_words: HashMap<String, u32>,
+    /// mode and compression of `tar` invocations, like `create:gz`, see [`tar_op`]
+    pub tar_ops: HashMap<String, u32>,
+    /// operations of the Nix tools, like `nix:run:nixpkgs#hello`, see [`nix_op`]
+    pub nix_ops: HashMap<String, u32>,
+    /// Nix commands with a flake reference, i.e. an argument containing `#`
+    pub nix_flake_commands: u64,
+    /// options given to git, like `--force`, see [`git_flags`]
+    pub git_flags: HashMap<String, u32>,
+    /// commands run in each directory, as far as it can be told from the `cd`s before them
+    pub commands_by_directory: HashMap<String, HashMap<String, u32>>,
+    /// commands run in each named terminal session, from `# session: <name>` marker lines in the history
+    pub sessions_by_name: HashMap<String, u32>,
+    /// tmux subcommands with their aliases resolved, like `attach-session` for `tmux a`
+    pub tmux_subcommands: HashMap<String, u32>,
+    /// how often the output of a command was piped into another, like `(grep, wc)`
+    pub pipe_transitions: HashMap<(String, String), u32>,
+    /// operations of the Ansible tools, like `ansible-playbook:site.yml`, see [`ansible_op`]
+    pub ansible_ops: HashMap<String, u32>,
+}
+
+impl State {
+    /// The number of local calendar days from the first day with a timestamp to the last one, both
+    /// included.
+    pub fn days(&self) -> Option<u64> {
+        let (oldest, newest) = self.time_span?;
+        let first = time::local_time(oldest)?.date.day_number();
+        let last = time::local_time(newest)?.date.day_number();
+        Some((last - first + 1) as u64)
+    }
+}
+
+/// Widens `span` to include `oldest..=newest`.
+fn extend_span(span: &mut Option<(u64, u64)>, oldest: u64, newest: u64) {
+    let (a, b) = span.get_or_insert((oldest, newest));
+    *a = oldest.min(*a);
+    *b = newest.max(*b);
+}
+
+/// Combining statistics gathered from different histories or runs.
+pub trait Merge {
+    fn merge(&mut self, other: Self);
+}
+
+impl Merge for u32 {
+    fn merge(&mut self, other: Self) {
+        *self += other;
+    }
+}
+
+impl Merge for u64 {
+    fn merge(&mut self, other: Self) {
+        *self += other;
+    }
+}
+
+impl<T> Merge for Vec<T> {
+    fn merge(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+impl<T: Merge, const N: usize> Merge for [T; N] {
+    fn merge(&mut self, other: Self) {
+        for (a, b) in self.iter_mut().zip(other) {
+            a.merge(b);
+        }
+    }
+}
+
+impl<K: Eq + std::hash::Hash, V: Merge> Merge for HashMap<K, V> {
+    fn merge(&mut self, other: Self) {
+        for (key, value) in other {
+            match self.entry(key) {
+                Entry::Occupied(entry) => entry.into_mut().merge(value),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+}
+
+// the impls below destructure `State` so that adding a field without handling it is a compile error
+
+impl Merge for State {
+    fn merge(&mut self, other: Self) {
+        let State {
+            man_pages,
+            git_subcommands,
+            commands,
+            command_durations,
+            time_span,
+            hourly_counts,
+            weekday_counts,
+            pipeline_depth_histogram,
+            sudo_count,
+            entry_timestamps,
+            package_manager_ops,
+            docker_ops,
+            k8s_ops,
+            ssh_hosts,
+            directories,
+            previous_dir_count,
+            editor_files,
+            editors,
+            commands_first_seen,
+            longest_command,
+            most_args_command,
+            command_bigrams,
+            total_commands,
+            analyzers,
+            cargo_subcommands,
+            npm_ops,
+            systemctl_ops,
+            likely_typos,
+            background_jobs,
+            backgrounded_commands,
+            search_patterns,
+            search_tools,
+            http_targets,
+            build_targets,
+            undo_count,
+            destructive_commands,
+            // derived, see below
+            danger_ratio: _,
+            weekly_counts,
+            venv_activations,
+            help_sought,
+            command_timestamps,
+            builtins,
+            cloud_ops,
+            jq_filter_lengths,
+            longest_jq_filter,
+            command_categories,
+            per_command_args,
+            terraform_ops,
+            argument_words,
+            tar_ops,
+            nix_ops,
+            nix_flake_commands,
+            git_flags,
+            commands_by_directory,
+            sessions_by_name,
+            tmux_subcommands,
+            pipe_transitions,
+            ansible_ops,
+        } = other;
+        self.man_pages.merge(man_pages);
+        self.git_subcommands.merge(git_subcommands);
+        self.commands.merge(commands);
+        self.command_durations.merge(command_durations);
+        if let Some((oldest, newest)) = time_span {
+            extend_span(&mut self.time_span, oldest, newest);
+        }
+        self.hourly_counts.merge(hourly_counts);
+        self.weekday_counts.merge(weekday_counts);
+        self.pipeline_depth_histogram
+            .merge(pipeline_depth_histogram);
+        self.sudo_count.merge(sudo_count);
+        self.entry_timestamps.merge(entry_timestamps);
+        self.package_manager_ops.merge(package_manager_ops);
+        self.docker_ops.merge(docker_ops);
+        self.k8s_ops.merge(k8s_ops);
+        self.ssh_hosts.merge(ssh_hosts);
+        self.directories.merge(directories);
+        self.previous_dir_count.merge(previous_dir_count);
+        self.editor_files.merge(editor_files);
+        self.editors.merge(editors);
+        // the earlier sighting wins rather than the sum
+        for (cmd, timestamp) in commands_first_seen {
+            self.commands_first_seen
+                .entry(cmd)
+                .and_modify(|x| *x = (*x).min(timestamp))
+                .or_insert(timestamp);
+        }
+        keep_largest(&mut self.longest_command, longest_command);
+        keep_largest(&mut self.most_args_command, most_args_command);
+        for analyzer in analyzers {
+            if let Some(analyzer) = analyzer::merge_into(&mut self.analyzers, analyzer) {
+                self.analyzers.push(analyzer);
+            }
+        }
+        self.command_bigrams.merge(command_bigrams);
+        self.total_commands.merge(total_commands);
+        self.cargo_subcommands.merge(cargo_subcommands);
+        self.npm_ops.merge(npm_ops);
+        self.systemctl_ops.merge(systemctl_ops);
+        // derived from the commands, which may have changed
+        drop(likely_typos);
+        detect_typos(self);
+        self.background_jobs.merge(background_jobs);
+        self.backgrounded_commands.merge(backgrounded_commands);
+        self.search_patterns.merge(search_patterns);
+        self.search_tools.merge(search_tools);
+        self.http_targets.merge(http_targets);
+        self.build_targets.merge(build_targets);
+        self.undo_count.merge(undo_count);
+        self.destructive_commands.merge(destructive_commands);
+        self.danger_ratio = compute_danger_ratio(self);
+        self.weekly_counts.merge(weekly_counts);
+        self.venv_activations.merge(venv_activations);
+        self.help_sought.merge(help_sought);
+        self.command_timestamps.merge(command_timestamps);
+        self.builtins.merge(builtins);
+        self.cloud_ops.merge(cloud_ops);
+        self.jq_filter_lengths.merge(jq_filter_lengths);
+        if let Some(filter) = longest_jq_filter {
+            record_jq_filter(self, filter);
+        }
+        self.command_categories.merge(command_categories);
+        self.per_command_args.merge(per_command_args);
+        self.terraform_ops.merge(terraform_ops);
+        self.argument_words.merge(argument_words);
+        self.tar_ops.merge(tar_ops);
+        self.nix_ops.merge(nix_ops);
+        self.nix_flake_commands.merge(nix_flake_commands);
+        self.git_flags.merge(git_flags);
+        self.commands_by_directory.merge(commands_by_directory);
+        self.sessions_by_name.merge(sessions_by_name);
+        self.tmux_subcommands.merge(tmux_subcommands);
+        self.pipe_transitions.merge(pipe_transitions);
+        self.ansible_ops.merge(ansible_ops);
+    }
+}
+
+/// Fills [`State::likely_typos`] from the counted commands. A command only counts as a typo if
+/// the command it is close to was run more often.
+pub fn detect_typos(state: &mut State) {
+    let mut typos: Vec<_> = state
+        .commands
+        .iter()
+        .filter_map(|(cmd, count)| {
+            let intended = distance::intended_command(cmd)?;
+            let intended_count = state.commands.get(intended).copied().unwrap_or(0);
+            (intended_count > *count).then(|| (cmd.clone(), intended.to_owned(), *count))
+        })
+        .collect();
+    typos.sort_unstable_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    state.likely_typos = typos;
+}
+
+/// The value of [`State::danger_ratio`], from the destructive commands and all command lines.
+fn compute_danger_ratio(state: &State) -> f64 {
+    match state.total_commands {
+        0 => 0.0,
+        total => state.destructive_commands as f64 / total as f64,
+    }
+}
+
+/// Replaces `current` by `candidate` if that has a larger second element.
+fn keep_largest<T>(current: &mut Option<(T, usize)>, candidate: Option<(T, usize)>) {
+    if let Some(candidate) = candidate {
+        if current.as_ref().is_none_or(|x| candidate.1 > x.1) {
+            *current = Some(candidate);
+        }
+    }
+}
+
+/// How the command counts of two [`State`]s differ, see [`diff_states`].
+#[derive(Debug, Default, PartialEq)]
+pub struct StateDiff {
+    /// commands only run in the second state, most frequent first
+    pub added: Vec<(String, u32)>,
+    /// commands only run in the first state, most frequent first
+    pub removed: Vec<(String, u32)>,
+    /// commands run in both but not equally often, with the counts in the first and the second,
+    /// largest change first
+    pub changed: Vec<(String, u32, u32)>,
+}
+
+/// Compares the commands of two histories, e.g. of a work and a personal machine, or from before
+/// and after switching shells.
+pub fn diff_states(a: &State, b: &State) -> StateDiff {
+    let only_in = |x: &State, y: &State| {
+        let mut only: Vec<_> = x
+            .commands
+            .iter()
+            .filter(|(cmd, _)| !y.commands.contains_key(*cmd))
+            .map(|(cmd, count)| (cmd.clone(), *count))
+            .collect();
+        only.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        only
+    };
+    let mut changed: Vec<_> = a
+        .commands
+        .iter()
+        .filter_map(|(cmd, before)| {
+            let after = *b.commands.get(cmd)?;
+            (after != *before).then(|| (cmd.clone(), *before, after))
+        })
+        .collect();
+    changed.sort_unstable_by(|x, y| {
+        y.1.abs_diff(y.2)
+            .cmp(&x.1.abs_diff(x.2))
+            .then_with(|| x.0.cmp(&y.0))
+    });
+    StateDiff {
+        added: only_in(b, a),
+        removed: only_in(a, b),
+        changed,
+    }
+}
+
+/// Combines the statistics of two histories, e.g. from different shells or machines.
+pub fn merge_states(mut a: State, b: State) -> State {
+    a.merge(b);
+    a
+}
+
+impl Persist for State {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let State {
+            man_pages,
+            git_subcommands,
+            commands,
+            command_durations,
+            time_span,
+            hourly_counts,
+            weekday_counts,
+            pipeline_depth_histogram,
+            sudo_count,
+            entry_timestamps,
+            package_manager_ops,
+            docker_ops,
+            k8s_ops,
+            ssh_hosts,
+            directories,
+            previous_dir_count,
+            editor_files,
+            editors,
+            commands_first_seen,
+            longest_command,
+            most_args_command,
+            command_bigrams,
+            total_commands,
+            analyzers,
+            cargo_subcommands,
+            npm_ops,
+            systemctl_ops,
+            likely_typos,
+            background_jobs,
+            backgrounded_commands,
+            search_patterns,
+            search_tools,
+            http_targets,
+            build_targets,
+            undo_count,
+            destructive_commands,
+            danger_ratio,
+            weekly_counts,
+            venv_activations,
+            help_sought,
+            command_timestamps,
+            builtins,
+            cloud_ops,
+            jq_filter_lengths,
+            longest_jq_filter,
+            command_categories,
+            per_command_args,
+            terraform_ops,
+            argument_words,
+            tar_ops,
+            nix_ops,
+            nix_flake_commands,
+            git_flags,
+            commands_by_directory,
+            sessions_by_name,
+            tmux_subcommands,
+            pipe_transitions,
+            ansible_ops,
+        } = self;
+        let mut fields = Fields::default();
+        // stored under a new name since the section was added, older stores had just the page
+        fields.set("man_pages_by_section", man_pages);
+        fields.set("git_subcommands", git_subcommands);
+        fields.set("commands", commands);
+        fields.set("command_durations", command_durations);
+        fields.set("time_span", time_span);
+        fields.set("hourly_counts", hourly_counts);
+        fields.set("weekday_counts", weekday_counts);
+        fields.set("pipeline_depth_histogram", pipeline_depth_histogram);
+        fields.set("sudo_count", sudo_count);
+        fields.set("entry_timestamps", entry_timestamps);
+        fields.set("package_manager_ops", package_manager_ops);
+        fields.set("docker_ops", docker_ops);
+        fields.set("k8s_ops", k8s_ops);
+        fields.set("ssh_hosts", ssh_hosts);
+        fields.set("directories", directories);
+        fields.set("previous_dir_count", previous_dir_count);
+        fields.set("editor_files", editor_files);
+        fields.set("editors", editors);
+        fields.set("commands_first_seen", commands_first_seen);
+        fields.set("longest_command", longest_command);
+        fields.set("most_args_command", most_args_command);
+        fields.set("command_bigrams", command_bigrams);
+        fields.set("total_commands", total_commands);
+        let analyzers: HashMap<String, Fields> = analyzers
+            .iter()
+            .filter_map(|analyzer| {
+                let mut fields = Fields::default();
+                analyzer.save(&mut fields);
+                Some((analyzer.name()?.to_owned(), fields))
+            })
+            .collect();
+        fields.set("analyzers", &analyzers);
+        fields.set("cargo_subcommands", cargo_subcommands);
+        fields.set("npm_ops", npm_ops);
+        fields.set("systemctl_ops", systemctl_ops);
+        fields.set("likely_typos", likely_typos);
+        fields.set("background_jobs", background_jobs);
+        fields.set("backgrounded_commands", backgrounded_commands);
+        fields.set("search_patterns", search_patterns);
+        fields.set("search_tools", search_tools);
+        fields.set("http_targets", http_targets);
+        fields.set("build_targets", build_targets);
+        fields.set("undo_count", undo_count);
+        fields.set("destructive_commands", destructive_commands);
+        fields.set("danger_ratio", danger_ratio);
+        fields.set("weekly_counts", weekly_counts);
+        fields.set("venv_activations", venv_activations);
+        fields.set("help_sought", help_sought);
+        fields.set("command_timestamps", command_timestamps);
+        fields.set("builtins", builtins);
+        fields.set("cloud_ops", cloud_ops);
+        fields.set("jq_filter_lengths", jq_filter_lengths);
+        fields.set("longest_jq_filter", longest_jq_filter);
+        fields.set("command_categories", command_categories);
+        fields.set("per_command_args", per_command_args);
+        fields.set("terraform_ops", terraform_ops);
+        fields.set("argument_words", argument_words);
+        fields.set("tar_ops", tar_ops);
+        fields.set("nix_ops", nix_ops);
+        fields.set("nix_flake_commands", nix_flake_commands);
+        fields.set("git_flags", git_flags);
+        fields.set("commands_by_directory", commands_by_directory);
+        fields.set("sessions_by_name", sessions_by_name);
+        fields.set("tmux_subcommands", tmux_subcommands);
+        fields.set("pipe_transitions", pipe_transitions);
+        fields.set("ansible_ops", ansible_ops);
+        fields.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let fields = Fields::decode(input)?;
+        Some(State {
+            man_pages: fields.get("man_pages_by_section")?,
+            git_subcommands: fields.get("git_subcommands")?,
+            commands: fields.get("commands")?,
+            command_durations: fields.get("command_durations")?,
+            time_span: fields.get("time_span")?,
+            hourly_counts: fields.get("hourly_counts")?,
+            weekday_counts: fields.get("weekday_counts")?,
+            pipeline_depth_histogram: fields.get("pipeline_depth_histogram")?,
+            sudo_count: fields.get("sudo_count")?,
+            entry_timestamps: fields.get("entry_timestamps")?,
+            package_manager_ops: fields.get("package_manager_ops")?,
+            docker_ops: fields.get("docker_ops")?,
+            k8s_ops: fields.get("k8s_ops")?,
+            ssh_hosts: fields.get("ssh_hosts")?,
+            directories: fields.get("directories")?,
+            previous_dir_count: fields.get("previous_dir_count")?,
+            editor_files: fields.get("editor_files")?,
+            editors: fields.get("editors")?,
+            commands_first_seen: fields.get("commands_first_seen")?,
+            longest_command: fields.get("longest_command")?,
+            most_args_command: fields.get("most_args_command")?,
+            command_bigrams: fields.get("command_bigrams")?,
+            total_commands: fields.get("total_commands")?,
+            analyzers: {
+                let saved: HashMap<String, Fields> = fields.get("analyzers")?;
+                let mut saved: Vec<_> = saved.into_iter().collect();
+                saved.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+                saved
+                    .iter()
+                    .filter_map(|(name, fields)| analyzer::load(name, fields))
+                    .collect()
+            },
+            cargo_subcommands: fields.get("cargo_subcommands")?,
+            npm_ops: fields.get("npm_ops")?,
+            systemctl_ops: fields.get("systemctl_ops")?,
+            likely_typos: fields.get("likely_typos")?,
+            background_jobs: fields.get("background_jobs")?,
+            backgrounded_commands: fields.get("backgrounded_commands")?,
+            search_patterns: fields.get("search_patterns")?,
+            search_tools: fields.get("search_tools")?,
+            http_targets: fields.get("http_targets")?,
+            build_targets: fields.get("build_targets")?,
+            undo_count: fields.get("undo_count")?,
+            destructive_commands: fields.get("destructive_commands")?,
+            danger_ratio: fields.get("danger_ratio")?,
+            weekly_counts: fields.get("weekly_counts")?,
+            venv_activations: fields.get("venv_activations")?,
+            help_sought: fields.get("help_sought")?,
+            command_timestamps: fields.get("command_timestamps")?,
+            builtins: fields.get("builtins")?,
+            cloud_ops: fields.get("cloud_ops")?,
+            jq_filter_lengths: fields.get("jq_filter_lengths")?,
+            longest_jq_filter: fields.get("longest_jq_filter")?,
+            command_categories: fields.get("command_categories")?,
+            per_command_args: fields.get("per_command_args")?,
+            terraform_ops: fields.get("terraform_ops")?,
+            argument_words: fields.get("argument_words")?,
+            tar_ops: fields.get("tar_ops")?,
+            nix_ops: fields.get("nix_ops")?,
+            nix_flake_commands: fields.get("nix_flake_commands")?,
+            git_flags: fields.get("git_flags")?,
+            commands_by_directory: fields.get("commands_by_directory")?,
+            sessions_by_name: fields.get("sessions_by_name")?,
+            tmux_subcommands: fields.get("tmux_subcommands")?,
+            pipe_transitions: fields.get("pipe_transitions")?,
+            ansible_ops: fields.get("ansible_ops")?,
+        })
+    }
+}
+
+impl ToJson for State {
+    fn write_json(&self, out: &mut String) {
+        let State {
+            man_pages,
+            git_subcommands,
+            commands,
+            command_durations,
+            time_span,
+            hourly_counts,
+            weekday_counts,
+            pipeline_depth_histogram,
+            sudo_count,
+            entry_timestamps,
+            package_manager_ops,
+            docker_ops,
+            k8s_ops,
+            ssh_hosts,
+            directories,
+            previous_dir_count,
+            editor_files,
+            editors,
+            commands_first_seen,
+            longest_command,
+            most_args_command,
+            command_bigrams,
+            total_commands,
+            analyzers,
+            cargo_subcommands,
+            npm_ops,
+            systemctl_ops,
+            likely_typos,
+            background_jobs,
+            backgrounded_commands,
+            search_patterns,
+            search_tools,
+            http_targets,
+            build_targets,
+            undo_count,
+            destructive_commands,
+            danger_ratio,
+            weekly_counts,
+            venv_activations,
+            help_sought,
+            command_timestamps,
+            builtins,
+            cloud_ops,
+            jq_filter_lengths,
+            longest_jq_filter,
+            command_categories,
+            per_command_args,
+            terraform_ops,
+            argument_words,
+            tar_ops,
+            nix_ops,
+            nix_flake_commands,
+            git_flags,
+            commands_by_directory,
+            sessions_by_name,
+            tmux_subcommands,
+            pipe_transitions,
+            ansible_ops,
+        } = self;
+        json::Object::new(out)
+            .field("man_pages", man_pages)
+            .field("git_subcommands", git_subcommands)
+            .field("commands", commands)
+            .field("command_durations", command_durations)
+            .field("time_span", time_span)
+            .field("hourly_counts", hourly_counts)
+            .field("weekday_counts", weekday_counts)
+            .field("pipeline_depth_histogram", pipeline_depth_histogram)
+            .field("sudo_count", sudo_count)
+            .field("entry_timestamps", entry_timestamps)
+            .field("package_manager_ops", package_manager_ops)
+            .field("docker_ops", docker_ops)
+            .field("k8s_ops", k8s_ops)
+            .field("ssh_hosts", ssh_hosts)
+            .field("directories", directories)
+            .field("previous_dir_count", previous_dir_count)
+            .field("editor_files", editor_files)
+            .field("editors", editors)
+            .field("commands_first_seen", commands_first_seen)
+            .field(
+                "longest_command",
+                &longest_command
+                    .as_ref()
+                    .map(|(cmd, len)| (String::from_utf8_lossy(cmd).into_owned(), *len)),
+            )
+            .field("most_args_command", most_args_command)
+            .field("command_bigrams", command_bigrams)
+            .field("total_commands", total_commands)
+            .field("analyzers", &analyzer::Named(analyzers))
+            .field("cargo_subcommands", cargo_subcommands)
+            .field("npm_ops", npm_ops)
+            .field("systemctl_ops", systemctl_ops)
+            .field("likely_typos", likely_typos)
+            .field("background_jobs", background_jobs)
+            .field("backgrounded_commands", backgrounded_commands)
+            .field("search_patterns", search_patterns)
+            .field("search_tools", search_tools)
+            .field("http_targets", http_targets)
+            .field("build_targets", build_targets)
+            .field("undo_count", undo_count)
+            .field("destructive_commands", destructive_commands)
+            .field("danger_ratio", danger_ratio)
+            .field("weekly_counts", weekly_counts)
+            .field("venv_activations", venv_activations)
+            .field("help_sought", help_sought)
+            .field("command_timestamps", command_timestamps)
+            .field("builtins", builtins)
+            .field("cloud_ops", cloud_ops)
+            .field("jq_filter_lengths", jq_filter_lengths)
+            .field("longest_jq_filter", longest_jq_filter)
+            .field("command_categories", command_categories)
+            .field("per_command_args", per_command_args)
+            .field("terraform_ops", terraform_ops)
+            .field("argument_words", argument_words)
+            .field("tar_ops", tar_ops)
+            .field("nix_ops", nix_ops)
+            .field("nix_flake_commands", nix_flake_commands)
+            .field("git_flags", git_flags)
+            .field("commands_by_directory", commands_by_directory)
+            .field("sessions_by_name", sessions_by_name)
+            .field("tmux_subcommands", tmux_subcommands)
+            .field("pipe_transitions", pipe_transitions)
+            .field("ansible_ops", ansible_ops)
+            .finish();
+    }
+}
+
+/// Whether the report is colored, with `Auto` deciding by whether stdout is a terminal.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parses `auto`, `always` or `never`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// The time frame given with `--since` and `--until`, as unix timestamps.
+#[derive(Default, Clone, Copy)]
+pub struct TimeRange {
+    pub since: Option<u64>,
+    /// exclusive
+    pub until: Option<u64>,
+}
+
+impl TimeRange {
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// How the time frame is referred to in the report, e.g. "in 2024".
+    pub fn describe(&self) -> String {
+        let date = |x: Option<u64>| x.and_then(time::local_time).map(|x| x.date);
+        match (date(self.since), date(self.until)) {
+            (None, None) => "ever".into(),
+            // exactly one calendar year
+            (Some(since), Some(until))
+                if (since.month, since.day, until.month, until.day) == (1, 1, 1, 1)
+                    && until.year == since.year + 1 =>
+            {
+                format!("in {}", since.year)
+            }
+            (Some(since), None) => format!("since {since}"),
+            (None, Some(until)) => format!("before {until}"),
+            (Some(since), Some(until)) => format!("between {since} and {until}"),
+        }
+    }
+
+    pub fn contains(&self, timestamp: u64) -> bool {
+        self.since.is_none_or(|x| timestamp >= x) && self.until.is_none_or(|x| timestamp < x)
+    }
+}
+
+/// When the operating system was probably installed: the oldest creation (or else modification)
+/// time of a few files written during installation. `None` if none of them can be read.
+pub fn install_time() -> Option<u64> {
+    [
+        "/etc/hostname",
+        "/var/log/installer/syslog",
+        "/proc/version",
+    ]
+    .into_iter()
+    .filter_map(|path| {
+        let metadata = std::fs::metadata(path).ok()?;
+        let secs = |x: std::time::SystemTime| {
+            let secs = x.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+            // file systems that do not record a creation time may report 0
+            (secs > 0).then_some(secs)
+        };
+        metadata
+            .created()
+            .ok()
+            .and_then(secs)
+            .or_else(|| metadata.modified().ok().and_then(secs))
+    })
+    .min()
+}
+
+/// Only yields the entries of `history` within `range`. Entries without a timestamp cannot be
+/// placed, so they are all kept, with a warning naming the history `source`.
+pub fn within_range<'a, H: CommandHistory + ?Sized>(
+    history: &'a mut H,
+    range: TimeRange,
+    source: &'a Path,
+) -> impl CommandHistory + 'a {
+    let mut warned = false;
+    history.filter(move |entry| match entry.timestamp {
+        _ if range.is_unbounded() => true,
+        Some(timestamp) => range.contains(timestamp),
+        None => {
+            if !warned {
+                eprintln!(
+                    "linux_wrapped: warning: {} has no timestamps, processing all of it despite --since/--until",
+                    source.display()
+                );
+                warned = true;
+            }
+            true
+        }
+    })
+}
+
+/// Entries of `map` ordered by descending count, ties broken by key.
+pub fn most_common<K: Ord, V: Ord + Copy>(map: &HashMap<K, V>) -> Vec<(V, &K)> {
+    let mut entries: Vec<_> = map.iter().map(|x| (*x.1, x.0)).collect();
+    entries.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    entries
+}
+
+/// `page(section)` as man pages are usually referred to, or just `page` without a section.
+pub fn man_page_label(section: u8, page: &str) -> String {
+    if section == 0 {
+        page.to_owned()
+    } else {
+        format!("{page}({section})")
+    }
+}
+
+/// The 50th, 90th and 99th percentile of `counts` by the nearest-rank method, zeros if it is empty.
+pub fn compute_percentiles(counts: &[u32]) -> (u32, u32, u32) {
+    if counts.is_empty() {
+        return (0, 0, 0);
+    }
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let percentile = |p: usize| sorted[(p * sorted.len()).div_ceil(100) - 1];
+    (percentile(50), percentile(90), percentile(99))
+}
+
+/// Git aliases from the oh-my-zsh git plugin and the subcommand they run.
+const GIT_ALIASES: &[(&str, &str)] = &[
+    ("ga", "add"),
+    ("gaa", "add"),
+    ("gapa", "add"),
+    ("gau", "add"),
+    ("gav", "add"),
+    ("gb", "branch"),
+    ("gba", "branch"),
+    ("gbd", "branch"),
+    ("gbD", "branch"),
+    ("gbl", "blame"),
+    ("gbs", "bisect"),
+    ("gc", "commit"),
+    ("gc!", "commit"),
+    ("gca", "commit"),
+    ("gca!", "commit"),
+    ("gcam", "commit"),
+    ("gcan!", "commit"),
+    ("gcmsg", "commit"),
+    ("gcn!", "commit"),
+    ("gcs", "commit"),
+    ("gcb", "checkout"),
+    ("gcd", "checkout"),
+    ("gcm", "checkout"),
+    ("gco", "checkout"),
+    ("gcf", "config"),
+    ("gcl", "clone"),
+    ("gclean", "clean"),
+    ("gcp", "cherry-pick"),
+    ("gcpa", "cherry-pick"),
+    ("gcpc", "cherry-pick"),
+    ("gd", "diff"),
+    ("gdca", "diff"),
+    ("gds", "diff"),
+    ("gdw", "diff"),
+    ("gf", "fetch"),
+    ("gfa", "fetch"),
+    ("gfo", "fetch"),
+    ("gl", "pull"),
+    ("gpr", "pull"),
+    ("gup", "pull"),
+    ("glg", "log"),
+    ("glgg", "log"),
+    ("glo", "log"),
+    ("glog", "log"),
+    ("glol", "log"),
+    ("glola", "log"),
+    ("gm", "merge"),
+    ("gma", "merge"),
+    ("gp", "push"),
+    ("gpd", "push"),
+    ("gpf", "push"),
+    ("gpf!", "push"),
+    ("gpsup", "push"),
+    ("gpu", "push"),
+    ("gr", "remote"),
+    ("gra", "remote"),
+    ("grv", "remote"),
+    ("grb", "rebase"),
+    ("grba", "rebase"),
+    ("grbc", "rebase"),
+    ("grbi", "rebase"),
+    ("grh", "reset"),
+    ("grhh", "reset"),
+    ("grm", "rm"),
+    ("grs", "restore"),
+    ("grst", "restore"),
+    ("grev", "revert"),
+    ("gsh", "show"),
+    ("gsps", "show"),
+    ("gsta", "stash"),
+    ("gstaa", "stash"),
+    ("gstd", "stash"),
+    ("gstl", "stash"),
+    ("gstp", "stash"),
+    ("gsb", "status"),
+    ("gss", "status"),
+    ("gst", "status"),
+    ("gsw", "switch"),
+    ("gswc", "switch"),
+    ("gta", "tag"),
+    ("gts", "tag"),
+    ("gtv", "tag"),
+    ("gwt", "worktree"),
+];
+
+/// The git subcommand that `alias` stands for, from the config file or the built-in table.
+pub fn git_alias<'a>(git_aliases: &'a HashMap<String, String>, alias: &str) -> Option<&'a str> {
+    match git_aliases.get(alias) {
+        Some(subcommand) => Some(subcommand),
+        None => GIT_ALIASES.iter().find(|x| x.0 == alias).map(|x| x.1),
+    }
+}
+
+const PACKAGE_MANAGERS: &[&str] = &[
+    "apt", "apt-get", "dnf", "pacman", "brew", "zypper", "emerge", "nix-env", "guix",
+];
+
+/// The `manager:subcommand` key of a package manager invocation. Managers whose operations are
+/// flags (`pacman -Syu`) use their first argument, the others their first non-flag argument.
+pub fn package_manager_op(words: &[Vec<u8>]) -> Option<String> {
+    let (manager, args) = words.split_first()?;
+    let manager = std::str::from_utf8(manager).ok()?;
+    if !PACKAGE_MANAGERS.contains(&manager) {
+        return None;
+    }
+    let op = match manager {
+        "pacman" | "nix-env" | "emerge" => args.first()?,
+        _ => args.iter().find(|x| !x.starts_with(b"-"))?,
+    };
+    Some(format!("{manager}:{}", std::str::from_utf8(op).ok()?))
+}
+
+/// Whether a `manager:subcommand` key installs or updates packages.
+pub fn changes_packages(op: &str) -> bool {
+    let Some((manager, subcommand)) = op.split_once(':') else {
+        return false;
+    };
+    match manager {
+        // queries like `-Ss` or `-Si` do not change anything
+        "pacman" => subcommand.starts_with("-S") && !subcommand.contains(['s', 'i', 'c', 'g']),
+        "nix-env" => matches!(subcommand, "-i" | "-iA" | "-u" | "--install" | "--upgrade"),
+        // `emerge <package>` installs it
+        "emerge" => !subcommand.starts_with('-') || subcommand.contains('u'),
+        _ => matches!(
+            subcommand,
+            "install"
+                | "reinstall"
+                | "upgrade"
+                | "update"
+                | "full-upgrade"
+                | "dist-upgrade"
+                | "dup"
+                | "in"
+                | "up"
+                | "refresh"
+                | "pull"
+        ),
+    }
+}
+
+/// Options of `docker run`, `pull` and `build` that take a value as the next word.
+const DOCKER_VALUE_OPTIONS: &[&str] = &[
+    "-e",
+    "--env",
+    "--env-file",
+    "-v",
+    "--volume",
+    "-p",
+    "--publish",
+    "--name",
+    "-w",
+    "--workdir",
+    "-u",
+    "--user",
+    "--network",
+    "--net",
+    "--entrypoint",
+    "--mount",
+    "-l",
+    "--label",
+    "--platform",
+    "-h",
+    "--hostname",
+    "-m",
+    "--memory",
+    "--cpus",
+    "--restart",
+    "--add-host",
+    "--device",
+    "--cap-add",
+    "--cap-drop",
+    "--log-driver",
+    "--ulimit",
+    "--gpus",
+    "--pull",
+    "--expose",
+    "--link",
+    "-t",
+    "--tag",
+    "-f",
+    "--file",
+    "--build-arg",
+    "--target",
+    "--cache-from",
+];
+
+/// The subcommand of a `docker` or `docker-compose` invocation, and for `run`, `pull` and `build`
+/// the image it uses. For `build` that is the `--tag`, since its positional argument is the
+/// build context.
+pub fn docker_op(words: &[Vec<u8>]) -> Option<(String, Option<String>)> {
+    let (cmd, args) = words.split_first()?;
+    if !matches!(cmd.as_slice(), b"docker" | b"docker-compose") {
+        return None;
+    }
+    container_op(args, cmd == b"docker")
+}
+
+/// The subcommand of a Docker-like CLI given its arguments, and with `images` the image used by
+/// `run`, `pull` and `build`, as well as by buildah's `from` and `bud`. Compose tools have no
+/// images here, since their subcommands act on services.
+pub(crate) fn container_op<T: AsRef<[u8]>>(
+    args: &[T],
+    images: bool,
+) -> Option<(String, Option<String>)> {
+    let position = args.iter().position(|x| !x.as_ref().starts_with(b"-"))?;
+    let subcommand = std::str::from_utf8(args[position].as_ref()).ok()?;
+    let subcommand = match subcommand {
+        "bud" => "build",
+        subcommand => subcommand,
+    };
+    if !images || !matches!(subcommand, "run" | "pull" | "build" | "from") {
+        return Some((subcommand.to_owned(), None));
+    }
+    let mut image = None;
+    let mut options = args[position + 1..].iter().map(AsRef::as_ref);
+    while let Some(word) = options.next() {
+        let word = std::str::from_utf8(word).ok()?;
+        if DOCKER_VALUE_OPTIONS.contains(&word) {
+            let value = options.next();
+            if subcommand == "build" && matches!(word, "-t" | "--tag") {
+                image = value.and_then(|x| std::str::from_utf8(x).ok());
+            }
+        } else if subcommand == "build" {
+            if let Some(tag) = word.strip_prefix("--tag=") {
+                image = Some(tag);
+            }
+        } else if !word.starts_with('-') {
+            image = Some(word);
+            break;
+        }
+    }
+    Some((subcommand.to_owned(), image.map(str::to_owned)))
+}
+
+/// The container a Docker-like subcommand such as `start`, `exec` or `logs` acts on, from the
+/// arguments after the subcommand.
+pub(crate) fn container_name<T: AsRef<[u8]>>(args: &[T]) -> Option<&str> {
+    let mut args = args.iter().map(AsRef::as_ref);
+    while let Some(word) = args.next() {
+        let word = std::str::from_utf8(word).ok()?;
+        if DOCKER_VALUE_OPTIONS.contains(&word) {
+            args.next();
+        } else if !word.starts_with('-') {
+            return Some(word);
+        }
+    }
+    None
+}
+
+/// Options of kubectl that take a value as the next word.
+const KUBECTL_VALUE_OPTIONS: &[&str] = &[
+    "-n",
+    "--namespace",
+    "--context",
+    "--cluster",
+    "--user",
+    "--kubeconfig",
+    "-l",
+    "--selector",
+    "-o",
+    "--output",
+    "-f",
+    "--filename",
+    "-c",
+    "--container",
+    "--field-selector",
+    "--since",
+    "--tail",
+    "--sort-by",
+];
+
+/// Resource types kubectl understands, in their singular, plural and short forms.
+const K8S_RESOURCES: &[&str] = &[
+    "pod",
+    "pods",
+    "po",
+    "deployment",
+    "deployments",
+    "deploy",
+    "service",
+    "services",
+    "svc",
+    "replicaset",
+    "replicasets",
+    "rs",
+    "statefulset",
+    "statefulsets",
+    "sts",
+    "daemonset",
+    "daemonsets",
+    "ds",
+    "job",
+    "jobs",
+    "cronjob",
+    "cronjobs",
+    "cj",
+    "configmap",
+    "configmaps",
+    "cm",
+    "secret",
+    "secrets",
+    "ingress",
+    "ingresses",
+    "ing",
+    "namespace",
+    "namespaces",
+    "ns",
+    "node",
+    "nodes",
+    "no",
+    "persistentvolume",
+    "persistentvolumes",
+    "pv",
+    "persistentvolumeclaim",
+    "persistentvolumeclaims",
+    "pvc",
+    "serviceaccount",
+    "serviceaccounts",
+    "sa",
+    "event",
+    "events",
+    "ev",
+    "endpoints",
+    "ep",
+    "role",
+    "roles",
+    "rolebinding",
+    "rolebindings",
+    "clusterrole",
+    "clusterroles",
+    "clusterrolebinding",
+    "clusterrolebindings",
+    "networkpolicy",
+    "networkpolicies",
+    "netpol",
+    "horizontalpodautoscaler",
+    "horizontalpodautoscalers",
+    "hpa",
+    "storageclass",
+    "storageclasses",
+    "sc",
+    "customresourcedefinition",
+    "customresourcedefinitions",
+    "crd",
+    "crds",
+    "all",
+];
+
+/// The `subcommand/resource` key of a `kubectl` (or `k`) invocation. The resource is `-` unless the
+/// first argument after the subcommand is a known resource type, optionally followed by
+/// `/name`.
+pub fn k8s_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    if !matches!(cmd.as_slice(), b"kubectl" | b"k") {
+        return None;
+    }
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(word) = args.next() {
+        let word = std::str::from_utf8(word).ok()?;
+        if KUBECTL_VALUE_OPTIONS.contains(&word) {
+            args.next();
+        } else if !word.starts_with('-') {
+            positional.push(word);
+            if positional.len() == 2 {
+                break;
+            }
+        }
+    }
+    let subcommand = positional.first()?;
+    let resource = positional
+        .get(1)
+        .map(|x| x.split('/').next().unwrap_or(x))
+        .filter(|x| K8S_RESOURCES.contains(x))
+        .unwrap_or("-");
+    Some(format!("{subcommand}/{resource}"))
+}
+
+/// Options of the JavaScript package managers that take a value as the next word.
+const NPM_VALUE_OPTIONS: &[&str] = &[
+    "--prefix",
+    "-w",
+    "--workspace",
+    "-C",
+    "--dir",
+    "--cwd",
+    "--filter",
+];
+
+/// The key of an `npm`, `yarn`, `pnpm` or `bun` invocation: `manager:subcommand`, and for the
+/// subcommands that run a script or package also its name, e.g. `npm:run:build`. `npx`, `pnpx`
+/// and `bunx` count as the `exec` subcommand of their manager.
+pub fn npm_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    let (manager, implied) = match cmd.as_slice() {
+        b"npm" => ("npm", None),
+        b"yarn" => ("yarn", None),
+        b"pnpm" => ("pnpm", None),
+        b"bun" => ("bun", None),
+        b"npx" => ("npm", Some("exec")),
+        b"pnpx" => ("pnpm", Some("dlx")),
+        b"bunx" => ("bun", Some("x")),
+        _ => return None,
+    };
+    let mut positional = implied.into_iter().collect::<Vec<_>>();
+    let mut args = args.iter();
+    while let Some(word) = args.next() {
+        let word = std::str::from_utf8(word).ok()?;
+        if NPM_VALUE_OPTIONS.contains(&word) {
+            args.next();
+        } else if !word.starts_with('-') {
+            positional.push(word);
+            if positional.len() == 2 {
+                break;
+            }
+        }
+    }
+    match positional.as_slice() {
+        // a bare `yarn` installs the dependencies
+        [] if manager == "yarn" => Some("yarn:install".to_owned()),
+        [] => None,
+        [subcommand @ ("run" | "run-script" | "exec" | "x" | "dlx"), name] => {
+            Some(format!("{manager}:{subcommand}:{name}"))
+        }
+        [subcommand, ..] => Some(format!("{manager}:{subcommand}")),
+    }
+}
+
+/// The `operation:unit` keys of a `systemctl` invocation that starts, stops or inspects units,
+/// one per unit named. The `.service` suffix is dropped so both spellings count together.
+pub fn systemctl_ops(words: &[Vec<u8>]) -> Vec<String> {
+    let Some((b"systemctl", args)) = words.split_first().map(|(a, b)| (a.as_slice(), b)) else {
+        return Vec::new();
+    };
+    let user = args.iter().any(|x| x == b"--user");
+    let mut positional = args
+        .iter()
+        .filter(|x| !x.starts_with(b"-"))
+        .filter_map(|x| std::str::from_utf8(x).ok());
+    let Some(op) = positional.next() else {
+        return Vec::new();
+    };
+    if !matches!(
+        op,
+        "start" | "stop" | "restart" | "enable" | "disable" | "status" | "reload"
+    ) {
+        return Vec::new();
+    }
+    positional
+        .map(|unit| {
+            let unit = unit.strip_suffix(".service").unwrap_or(unit);
+            if user {
+                format!("{op}:{unit} (user)")
+            } else {
+                format!("{op}:{unit}")
+            }
+        })
+        .collect()
+}
+
+/// Whether `rm` is told to delete recursively and without asking, as in `rm -rf` or `rm -r -f`.
+fn is_rm_rf(args: &[Vec<u8>]) -> bool {
+    let flags = |long: &[u8], short: u8| {
+        args.iter().any(|x| {
+            x == long || (x.starts_with(b"-") && !x.starts_with(b"--") && x.contains(&short))
+        })
+    };
+    (flags(b"--recursive", b'r') || flags(b"--recursive", b'R')) && flags(b"--force", b'f')
+}
+
+/// Whether a command undoes earlier work: `git reset`, `git revert`, discarding changes with
+/// `git checkout -- <paths>`, `rm -rf` and `unlink`.
+pub fn is_undo(words: &[Vec<u8>]) -> bool {
+    let Some((cmd, args)) = words.split_first() else {
+        return false;
+    };
+    match cmd.as_slice() {
+        b"git" => match args.first().map(Vec::as_slice) {
+            Some(b"reset" | b"revert") => true,
+            Some(b"checkout") => args.iter().any(|x| x == b"--"),
+            _ => false,
+        },
+        b"rm" => is_rm_rf(args),
+        b"unlink" => true,
+        _ => false,
+    }
+}
+
+/// Whether a command can destroy data without a way back: `rm -rf`, `truncate` and `dd`.
+pub fn is_destructive(words: &[Vec<u8>]) -> bool {
+    let Some((cmd, args)) = words.split_first() else {
+        return false;
+    };
+    match cmd.as_slice() {
+        b"rm" => is_rm_rf(args),
+        b"truncate" | b"dd" => true,
+        _ => false,
+    }
+}
+
+/// Global options of the cloud CLIs that take a value as the next word, unless written as
+/// `--option=value`.
+const CLOUD_VALUE_OPTIONS: &[&str] = &[
+    "--profile",
+    "--region",
+    "--output",
+    "--endpoint-url",
+    "--query",
+    "--cli-input-json",
+    "--project",
+    "--account",
+    "--configuration",
+    "--format",
+    "--filter",
+    "--zone",
+    "--subscription",
+    "--resource-group",
+    "-g",
+    "-o",
+    "-n",
+    "--name",
+];
+
+/// The key of an `aws`, `gcloud` or `az` invocation: the CLI followed by its two levels of
+/// subcommands, like `aws:s3:cp`, `gcloud:compute:instances` or `az:vm:create`. gcloud's `alpha`
+/// and `beta` tracks count with the stable one. Terraform has its own [`terraform_op`].
+pub fn cloud_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    let cli = match cmd.as_slice() {
+        b"aws" => "aws",
+        b"gcloud" => "gcloud",
+        b"az" => "az",
+        _ => return None,
+    };
+    let mut positional = Vec::new();
+    let mut args = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    while let Some(word) = args.next() {
+        if CLOUD_VALUE_OPTIONS.contains(&word) {
+            args.next();
+        } else if word.starts_with('-')
+            || (cli == "gcloud" && positional.is_empty() && matches!(word, "alpha" | "beta"))
+        {
+            continue;
+        } else {
+            positional.push(word.to_ascii_lowercase());
+            if positional.len() == 2 {
+                break;
+            }
+        }
+    }
+    if positional.is_empty() {
+        return None;
+    }
+    Some(format!("{cli}:{}", positional.join(":")))
+}
+
+/// The subcommand of a `terraform` (or `tf`, or OpenTofu's `tofu`) invocation. For `workspace`,
+/// its action and the workspace name are appended, as in `workspace:select:prod`.
+pub fn terraform_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    if !matches!(cmd.as_slice(), b"terraform" | b"tf" | b"tofu") {
+        return None;
+    }
+    let mut positional = args
+        .iter()
+        .filter(|x| !x.starts_with(b"-"))
+        .filter_map(|x| std::str::from_utf8(x).ok());
+    let subcommand = positional.next()?;
+    if subcommand != "workspace" {
+        return Some(subcommand.to_owned());
+    }
+    Some(
+        std::iter::once(subcommand)
+            .chain(positional.take(2))
+            .collect::<Vec<_>>()
+            .join(":"),
+    )
+}
+
+/// The options of a `git` invocation, before and after the subcommand, without their values,
+/// so `--format=%h` counts as `--format`. Bundled short options like `-am` are kept as typed.
+/// Everything after `--` is a path, not an option.
+pub fn git_flags<'a>(tokens: &[&'a [u8]]) -> Vec<&'a str> {
+    let Some((b"git", args)) = tokens.split_first().map(|(a, b)| (*a, b)) else {
+        return Vec::new();
+    };
+    args.iter()
+        .take_while(|x| **x != b"--")
+        .filter(|x| x.starts_with(b"-") && x.len() > 1)
+        .filter_map(|x| std::str::from_utf8(x).ok())
+        .map(|x| x.split('=').next().unwrap_or(x))
+        .collect()
+}
+
+/// The operation of a Nix tool: the subcommand of `nix`, like `nix:build`, with the flake that
+/// is run for `nix run`, as in `nix:run:nixpkgs#hello`, the action of `nixos-rebuild`, like
+/// `nixos-rebuild:switch`, and the operation of `nix-env`, like `nix-env:install`. `nix-shell` and
+/// `nix-build` have no subcommands.
+pub fn nix_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    let cmd = std::str::from_utf8(cmd).ok()?;
+    let mut positional = args
+        .iter()
+        .filter(|x| !x.starts_with(b"-"))
+        .filter_map(|x| std::str::from_utf8(x).ok());
+    match cmd {
+        "nix" => {
+            let subcommand = positional.next()?;
+            match positional.next().filter(|_| subcommand == "run") {
+                Some(flake) => Some(format!("nix:run:{flake}")),
+                None => Some(format!("nix:{subcommand}")),
+            }
+        }
+        "nixos-rebuild" => Some(format!("nixos-rebuild:{}", positional.next()?)),
+        "nix-env" => {
+            let operation = args.iter().find_map(|x| match x.as_slice() {
+                b"--install" => Some("install"),
+                b"--uninstall" => Some("uninstall"),
+                b"--upgrade" => Some("upgrade"),
+                b"--query" => Some("query"),
+                b"--rollback" => Some("rollback"),
+                x if x.starts_with(b"--") || !x.starts_with(b"-") => None,
+                // the operation comes first in a bundle like `-iA`
+                x => match x.get(1) {
+                    Some(b'i') => Some("install"),
+                    Some(b'e') => Some("uninstall"),
+                    Some(b'u') => Some("upgrade"),
+                    Some(b'q') => Some("query"),
+                    _ => None,
+                },
+            });
+            Some(format!("nix-env:{}", operation.unwrap_or("other")))
+        }
+        "nix-shell" | "nix-build" => Some(cmd.to_owned()),
+        _ => None,
+    }
+}
+
+/// Options of the Ansible tools that take the next word as their value.
+const ANSIBLE_VALUE_OPTIONS: &[&str] = &[
+    "-i",
+    "--inventory",
+    "-e",
+    "--extra-vars",
+    "-l",
+    "--limit",
+    "-t",
+    "--tags",
+    "--skip-tags",
+    "-u",
+    "--user",
+    "-f",
+    "--forks",
+    "-a",
+    "--args",
+    "-m",
+    "--module-name",
+    "-M",
+    "--module-path",
+    "-c",
+    "--connection",
+    "--private-key",
+    "--vault-id",
+    "--vault-password-file",
+    "-r",
+    "--role-file",
+    "-p",
+    "--roles-path",
+];
+
+/// The operation of an Ansible tool: the module that `ansible` runs, like `ansible:ping`, which is
+/// `command` unless `-m` is given, the playbook that `ansible-playbook` runs without its
+/// directory, like `ansible-playbook:site.yml`, and the subcommand of `ansible-galaxy`, like
+/// `ansible-galaxy:collection:install`. Without a host pattern, playbook or subcommand, only the
+/// tool is returned.
+pub fn ansible_op(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    let cmd = std::str::from_utf8(cmd).ok()?;
+    if !matches!(cmd, "ansible" | "ansible-playbook" | "ansible-galaxy") {
+        return None;
+    }
+    let mut module = None;
+    let mut positional = Vec::new();
+    let mut args = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    while let Some(arg) = args.next() {
+        if ANSIBLE_VALUE_OPTIONS.contains(&arg) {
+            let value = args.next();
+            if matches!(arg, "-m" | "--module-name") {
+                module = value;
+            }
+        } else if let Some(value) = arg.strip_prefix("--module-name=") {
+            module = Some(value);
+        } else if let Some(value) = arg.strip_prefix("-m").filter(|x| !x.is_empty()) {
+            module = Some(value);
+        } else if !arg.starts_with('-') {
+            positional.push(arg);
+        }
+    }
+    let op = match cmd {
+        "ansible" => match (module, positional.first()) {
+            (Some(module), _) => module.to_owned(),
+            (None, Some(_)) => "command".to_owned(),
+            (None, None) => return Some(cmd.to_owned()),
+        },
+        "ansible-playbook" => {
+            let playbook = positional
+                .iter()
+                .find(|x| x.ends_with(".yml") || x.ends_with(".yaml"));
+            match playbook {
+                Some(playbook) => playbook.rsplit('/').next().unwrap_or(playbook).to_owned(),
+                None => return Some(cmd.to_owned()),
+            }
+        }
+        _ => match positional.as_slice() {
+            [kind @ ("collection" | "role"), action, ..] => format!("{kind}:{action}"),
+            [subcommand, ..] => subcommand.to_string(),
+            [] => return Some(cmd.to_owned()),
+        },
+    };
+    Some(format!("{cmd}:{op}"))
+}
+
+/// The name in a `# session: <name>` comment, which some setups write to the history when a
+/// tmux or screen session starts so the commands after it can be told apart.
+pub fn session_marker(command: &[u8]) -> Option<&str> {
+    let comment = std::str::from_utf8(command)
+        .ok()?
+        .trim()
+        .strip_prefix('#')?;
+    let name = comment.trim_start().strip_prefix("session:")?.trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Aliases of tmux subcommands, which also accepts any unambiguous prefix.
+const TMUX_ALIASES: &[(&str, &str)] = &[
+    ("a", "attach-session"),
+    ("at", "attach-session"),
+    ("attach", "attach-session"),
+    ("new", "new-session"),
+    ("ls", "list-sessions"),
+    ("kill", "kill-session"),
+    ("detach", "detach-client"),
+    ("neww", "new-window"),
+    ("splitw", "split-window"),
+    ("source", "source-file"),
+    ("switch", "switch-client"),
+    ("rename", "rename-session"),
+];
+
+/// The subcommand of a `tmux` invocation, with aliases resolved. Without one, tmux starts a new
+/// session.
+pub fn tmux_subcommand(words: &[Vec<u8>]) -> Option<String> {
+    let (b"tmux", args) = words.split_first().map(|(a, b)| (a.as_slice(), b))? else {
+        return None;
+    };
+    let mut args = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    // options before the subcommand, of which these take a value
+    let subcommand = loop {
+        match args.next() {
+            Some("-L" | "-S" | "-f" | "-c" | "-T") => {
+                args.next();
+            }
+            Some(x) if x.starts_with('-') => {}
+            Some(x) => break x,
+            None => return Some("new-session".into()),
+        }
+    };
+    let resolved = TMUX_ALIASES.iter().find(|x| x.0 == subcommand);
+    Some(resolved.map_or(subcommand, |x| x.1).to_owned())
+}
+
+/// Short options of `tar` that take a value, which is the rest of the option or the next word.
+const TAR_VALUE_OPTIONS: &[u8] = b"bCfFgHIKLNTVX";
+
+/// The mode of a `tar` invocation and the compression it uses, as in `create:gz` or
+/// `extract:tar` for an uncompressed archive. Appending and updating count as creating. The flags
+/// can be bundled with or without a leading dash, as in `tar xzf` and `tar -xzf`, or be long
+/// options. `auto` is for `-a`, which picks the compression by the archive's suffix.
+pub fn tar_op(words: &[Vec<u8>]) -> Option<String> {
+    let (b"tar", args) = words.split_first().map(|(a, b)| (a.as_slice(), b))? else {
+        return None;
+    };
+    let (mut mode, mut format) = (None, "tar");
+    for (i, arg) in args.iter().enumerate() {
+        let flags: &[u8] = match arg.strip_prefix(b"--") {
+            Some(long) => {
+                let long = long.split(|x| *x == b'=').next().unwrap_or_default();
+                match long {
+                    b"create" | b"append" | b"update" => mode = Some("create"),
+                    b"extract" | b"get" => mode = Some("extract"),
+                    b"list" => mode = Some("list"),
+                    b"gzip" => format = "gz",
+                    b"bzip2" => format = "bz2",
+                    b"xz" => format = "xz",
+                    b"zstd" => format = "zst",
+                    b"auto-compress" => format = "auto",
+                    _ => {}
+                }
+                continue;
+            }
+            None => match arg.strip_prefix(b"-") {
+                Some(flags) => flags,
+                // the old style without a dash, only as the first argument
+                None if i == 0 => arg,
+                None => continue,
+            },
+        };
+        for flag in flags {
+            match flag {
+                b'c' | b'r' | b'u' => mode = Some("create"),
+                b'x' => mode = Some("extract"),
+                b't' => mode = Some("list"),
+                b'z' => format = "gz",
+                b'j' => format = "bz2",
+                b'J' => format = "xz",
+                b'a' => format = "auto",
+                // the rest of a dashed option is its value, like the archive in `-fa.tar`
+                x if TAR_VALUE_OPTIONS.contains(x) && arg.starts_with(b"-") => break,
+                _ => {}
+            }
+        }
+    }
+    Some(format!("{}:{format}", mode?))
+}
+
+/// Options of `jq` followed by the number of words they take as values.
+const JQ_VALUE_OPTIONS: &[(&str, usize)] = &[
+    ("--arg", 2),
+    ("--argjson", 2),
+    ("--slurpfile", 2),
+    ("--rawfile", 2),
+    ("--indent", 1),
+    ("-L", 1),
+];
+
+/// The filter of a `jq` invocation, its first argument that is not an option. `None` if the
+/// filter is read from a file with `-f`.
+pub fn jq_filter(words: &[Vec<u8>]) -> Option<&str> {
+    let (b"jq", args) = words.split_first().map(|(a, b)| (a.as_slice(), b))? else {
+        return None;
+    };
+    let mut args = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    while let Some(word) = args.next() {
+        if let Some((_, values)) = JQ_VALUE_OPTIONS.iter().find(|x| x.0 == word) {
+            args.nth(values - 1);
+        } else if matches!(word, "-f" | "--from-file") {
+            return None;
+        } else if !word.starts_with('-') {
+            return Some(word);
+        }
+    }
+    None
+}
+
+/// Counts the length of a `jq` filter and keeps it if it is the longest so far.
+fn record_jq_filter(state: &mut State, filter: String) {
+    if state
+        .longest_jq_filter
+        .as_ref()
+        .is_none_or(|x| filter.len() > x.len())
+    {
+        state.longest_jq_filter = Some(filter);
+    }
+}
+
+/// Options of `make` and `ninja` that take a value as the next word.
+const BUILD_VALUE_OPTIONS: &[&str] = &[
+    "-C",
+    "--directory",
+    "-f",
+    "--file",
+    "--makefile",
+    "-I",
+    "--include-dir",
+    "-j",
+    "--jobs",
+    "-l",
+    "--load-average",
+    "-o",
+    "--old-file",
+    "-W",
+    "--what-if",
+    "-k",
+];
+
+/// The `tool:target` keys of a `make`, `ninja`, `cmake --build` or `meson compile` invocation,
+/// one per target named, or `tool:default` if none was. Variable assignments like `CC=clang`
+/// are not targets.
+pub fn build_targets(words: &[Vec<u8>]) -> Vec<String> {
+    let Some((cmd, args)) = words.split_first() else {
+        return Vec::new();
+    };
+    let Ok(tool) = std::str::from_utf8(cmd) else {
+        return Vec::new();
+    };
+    let mut words = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    let mut targets = Vec::new();
+    match tool {
+        // `ninja -t` runs a tool instead of building
+        "ninja" if args.iter().any(|x| x == b"-t") => return Vec::new(),
+        "make" | "gmake" | "ninja" => {
+            while let Some(word) = words.next() {
+                if BUILD_VALUE_OPTIONS.contains(&word) {
+                    // `-j` and `-k` may also stand alone
+                    if !matches!(word, "-j" | "-k")
+                        || words
+                            .clone()
+                            .next()
+                            .is_some_and(|x| x.bytes().all(|x| x.is_ascii_digit()))
+                    {
+                        words.next();
+                    }
+                } else if !word.starts_with('-') && !word.contains('=') {
+                    targets.push(word);
+                }
+            }
+        }
+        "cmake" => {
+            let mut words = words.peekable();
+            if words.peek().copied() != Some("--build") {
+                return Vec::new();
+            }
+            while let Some(word) = words.next() {
+                if matches!(word, "--target" | "-t") {
+                    // several targets may follow one `--target`
+                    while let Some(target) = words.next_if(|x| !x.starts_with('-')) {
+                        targets.push(target);
+                    }
+                }
+            }
+        }
+        "meson" => {
+            let mut words = words.skip_while(|x| x.starts_with('-'));
+            if words.next() != Some("compile") {
+                return Vec::new();
+            }
+            while let Some(word) = words.next() {
+                if matches!(word, "-C" | "-j" | "--jobs" | "-l" | "--load-average") {
+                    words.next();
+                } else if !word.starts_with('-') {
+                    targets.push(word);
+                }
+            }
+        }
+        _ => return Vec::new(),
+    }
+    let tool = if tool == "gmake" { "make" } else { tool };
+    if targets.is_empty() {
+        return vec![format!("{tool}:default")];
+    }
+    targets
+        .into_iter()
+        .map(|target| format!("{tool}:{target}"))
+        .collect()
+}
+
+/// Options of grep-like tools that take a value as the next word, other than the pattern.
+const GREP_VALUE_OPTIONS: &[&str] = &[
+    "-A",
+    "-B",
+    "-C",
+    "-m",
+    "--max-count",
+    "-t",
+    "--type",
+    "-T",
+    "--type-not",
+    "-g",
+    "--glob",
+    "--include",
+    "--exclude",
+    "--exclude-dir",
+    "-j",
+    "--threads",
+    "-M",
+    "--max-columns",
+    "--color",
+    "--colors",
+    "-d",
+    "--max-depth",
+];
+
+/// The search tool and the patterns of a `grep`, `rg`, `ag`, `ack`, `find` or `fd` invocation.
+/// For the grep-like tools that is the first argument that is not an option or the values of
+/// `-e`, for `find` the values of `-name` and `-path` and their case-insensitive versions.
+pub fn search(words: &[Vec<u8>]) -> Option<(&str, Vec<String>)> {
+    let (tool, args) = words.split_first()?;
+    let tool = std::str::from_utf8(tool).ok()?;
+    let args = args.iter().filter_map(|x| std::str::from_utf8(x).ok());
+    let mut patterns = Vec::new();
+    match tool {
+        "grep" | "egrep" | "fgrep" | "rg" | "ripgrep" | "ag" | "ack" | "fd" => {
+            let mut args = args;
+            let mut explicit = false;
+            let mut positional = None;
+            while let Some(arg) = args.next() {
+                // for fd, `-e` is a file extension
+                let fd_value = matches!(arg, "-e" | "--extension" | "-E" | "--exclude");
+                if tool == "fd" && fd_value {
+                    args.next();
+                } else if matches!(arg, "-e" | "--regexp") {
+                    patterns.extend(args.next().map(str::to_owned));
+                    explicit = true;
+                } else if let Some(pattern) = arg.strip_prefix("--regexp=") {
+                    patterns.push(pattern.to_owned());
+                    explicit = true;
+                } else if GREP_VALUE_OPTIONS.contains(&arg) {
+                    args.next();
+                } else if arg == "--" {
+                    positional = positional.or(args.next());
+                    break;
+                } else if !arg.starts_with('-') && positional.is_none() {
+                    positional = Some(arg);
+                }
+            }
+            // with `-e` the first positional argument is a file instead
+            if !explicit {
+                patterns.extend(positional.map(str::to_owned));
+            }
+        }
+        "find" => {
+            let mut args = args;
+            while let Some(arg) = args.next() {
+                if matches!(arg, "-name" | "-iname" | "-path" | "-ipath") {
+                    patterns.extend(args.next().map(str::to_owned));
+                }
+            }
+        }
+        _ => return None,
+    }
+    Some((tool, patterns))
+}
+
+/// The host of a URL like `https://user@example.com:8080/path?query`, i.e. `example.com`. A URL
+/// without a scheme is accepted if its host looks like a domain name, since curl and wget accept
+/// those too.
+pub fn extract_host(url: &[u8]) -> Option<String> {
+    let url = std::str::from_utf8(url).ok()?;
+    let (rest, has_scheme) = match url.split_once("://") {
+        Some((_, rest)) => (rest, true),
+        None => (url, false),
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit('@').next()?;
+    let host = match host_port.strip_prefix('[') {
+        // IPv6 addresses are bracketed because they contain colons
+        Some(v6) => v6.split(']').next()?,
+        None => host_port.split(':').next()?,
+    };
+    let looks_like_host = host.contains('.') || host == "localhost";
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || matches!(x, '.' | '-' | '_' | ':'));
+    (valid && (has_scheme || looks_like_host)).then(|| host.to_ascii_lowercase())
+}
+
+/// Options of `curl` and `wget` that take a value as the next word.
+const HTTP_VALUE_OPTIONS: &[&str] = &[
+    "-A",
+    "-b",
+    "-c",
+    "-d",
+    "-e",
+    "-F",
+    "-H",
+    "-K",
+    "-m",
+    "-o",
+    "-O",
+    "-P",
+    "-T",
+    "-u",
+    "-U",
+    "-w",
+    "-x",
+    "-X",
+    "--data",
+    "--data-binary",
+    "--data-raw",
+    "--data-urlencode",
+    "--form",
+    "--header",
+    "--output",
+    "--output-document",
+    "--proxy",
+    "--referer",
+    "--request",
+    "--user",
+    "--user-agent",
+    "--cookie",
+    "--cookie-jar",
+    "--max-time",
+    "--write-out",
+    "--upload-file",
+    "--directory-prefix",
+    "--config",
+    "--json",
+];
+
+/// The hosts a `curl` or `wget` invocation fetches from.
+pub fn http_targets(words: &[Vec<u8>]) -> Vec<String> {
+    let Some((cmd, args)) = words.split_first() else {
+        return Vec::new();
+    };
+    if !matches!(cmd.as_slice(), b"curl" | b"wget") {
+        return Vec::new();
+    }
+    let mut hosts = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let option = std::str::from_utf8(arg).unwrap_or_default();
+        if HTTP_VALUE_OPTIONS.contains(&option) {
+            args.next();
+        } else if !arg.starts_with(b"-") {
+            hosts.extend(extract_host(arg));
+        }
+    }
+    hosts
+}
+
+/// The hosts an `ssh`, `scp` or `rsync` invocation connects to, without `user@` or `:path`. For
+/// `scp` and `rsync` only arguments with a `:` are remote, the others are local paths.
+pub fn ssh_hosts(words: &[Vec<u8>]) -> Vec<String> {
+    let Some((cmd, args)) = words.split_first() else {
+        return Vec::new();
+    };
+    // options taking a value as the next word
+    let value_options: &[&[u8]] = match cmd.as_slice() {
+        b"ssh" => &[
+            b"-p", b"-i", b"-J", b"-l", b"-o", b"-F", b"-L", b"-R", b"-D", b"-b", b"-c", b"-E",
+            b"-e", b"-m", b"-O", b"-Q", b"-S", b"-W", b"-w", b"-B",
+        ],
+        b"scp" => &[b"-P", b"-i", b"-J", b"-o", b"-F", b"-l", b"-S", b"-c"],
+        b"rsync" => &[
+            b"-e",
+            b"--rsh",
+            b"--exclude",
+            b"--include",
+            b"-f",
+            b"--filter",
+        ],
+        _ => return Vec::new(),
+    };
+    let mut hosts = Vec::new();
+    let mut args = args.iter();
+    while let Some(word) = args.next() {
+        if value_options.contains(&word.as_slice()) {
+            args.next();
+            continue;
+        }
+        let Ok(word) = std::str::from_utf8(word) else {
+            continue;
+        };
+        if word.starts_with('-') || (cmd != b"ssh" && !word.contains(':')) {
+            continue;
+        }
+        let word = word.strip_prefix("ssh://").unwrap_or(word);
+        let host = word.rsplit_once('@').map_or(word, |x| x.1);
+        let host = host.split([':', '/']).next().unwrap_or(host);
+        if !host.is_empty() {
+            hosts.push(host.to_owned());
+        }
+        if cmd == b"ssh" {
+            // the rest is the remote command
+            break;
+        }
+    }
+    hosts
+}
+
+/// Where a `cd` goes, as far as it is known without the current directory.
+enum CdTarget {
+    /// `cd -`
+    Previous,
+    /// the target with `~` expanded, the home directory if none was given
+    Path(String),
+}
+
+/// The target of a `cd`, `None` if it is not UTF-8 or refers to an unknown home directory.
+fn resolve_cd_target(target: Option<&[u8]>) -> Option<CdTarget> {
+    let home = || home::home_dir().map(|x| x.to_string_lossy().into_owned());
+    let target = match target {
+        None => return home().map(CdTarget::Path),
+        Some(b"-") => return Some(CdTarget::Previous),
+        Some(target) => std::str::from_utf8(target).ok()?,
+    };
+    Some(CdTarget::Path(match target.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{rest}", home()?),
+        _ => target.to_owned(),
+    }))
+}
+
+/// The directory `cd target` changes to from `current`, with `~` expanded and `.` and `..`
+/// resolved. `None` if it cannot be known, like for a relative path from an unknown directory.
+/// `previous` is where `cd -` goes back to.
+fn change_directory(
+    current: Option<&str>,
+    previous: Option<&str>,
+    target: Option<&[u8]>,
+) -> Option<String> {
+    let path = match resolve_cd_target(target)? {
+        CdTarget::Previous => return previous.map(str::to_owned),
+        CdTarget::Path(path) if path.starts_with('/') => path,
+        CdTarget::Path(path) => format!("{}/{path}", current?),
+    };
+    let mut components = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    Some(format!("/{}", components.join("/")))
+}
+
+/// Counts the directory a `cd`, `z` or `zoxide add` changes to. Without a target that is the home
+/// directory, and `cd -` going back is counted on its own.
+fn record_directory(state: &mut State, words: &[Vec<u8>]) {
+    let target = match words {
+        [cmd, rest @ ..] if cmd == b"cd" || cmd == b"z" => rest.first(),
+        [cmd, subcommand, rest @ ..] if cmd == b"zoxide" && subcommand == b"add" => rest.first(),
+        _ => return,
+    };
+    match resolve_cd_target(target.map(Vec::as_slice)) {
+        Some(CdTarget::Previous) => state.previous_dir_count += 1,
+        Some(CdTarget::Path(directory)) => *state.directories.entry(directory).or_default() += 1,
+        None => {}
+    }
+}
+
+const EDITORS: &[&str] = &["vim", "nvim", "vi", "emacs", "nano", "hx", "code", "subl"];
+
+/// Commands whose first argument that is not an option is a subcommand rather than an argument.
+const SUBCOMMAND_TOOLS: &[&str] = &[
+    "apt",
+    "aws",
+    "az",
+    "brew",
+    "cargo",
+    "dnf",
+    "docker",
+    "gcloud",
+    "gh",
+    "git",
+    "go",
+    "helm",
+    "kubectl",
+    "npm",
+    "pacman",
+    "pip",
+    "pnpm",
+    "podman",
+    "systemctl",
+    "terraform",
+    "yarn",
+];
+
+/// Counts the arguments of a command for [`State::per_command_args`], each once per invocation.
+fn record_args(state: &mut State, words: &[Vec<u8>]) {
+    let Some((cmd, args)) = words.split_first() else {
+        return;
+    };
+    let Ok(cmd) = std::str::from_utf8(cmd) else {
+        return;
+    };
+    let mut args: Vec<&str> = args
+        .iter()
+        .filter_map(|x| std::str::from_utf8(x).ok())
+        .collect();
+    if SUBCOMMAND_TOOLS.contains(&cmd) {
+        if let Some(i) = args.iter().position(|x| !x.starts_with('-')) {
+            args.remove(i);
+        }
+    }
+    args.sort_unstable();
+    args.dedup();
+    if args.is_empty() {
+        return;
+    }
+    let counts = state.per_command_args.entry(cmd.to_owned()).or_default();
+    for arg in args {
+        *counts.entry(arg.to_owned()).or_default() += 1;
+    }
+}
+
+/// An argument as counted in [`State::argument_words`], `None` for flags like `-x` and `--foo`
+/// and for anything that looks like a path.
+pub fn argument_word(word: &[u8]) -> Option<&str> {
+    let word = std::str::from_utf8(word).ok()?;
+    (!word.is_empty() && !word.starts_with('-') && !word.contains('/')).then_some(word)
+}
+
+/// Common builtins of POSIX shells, bash and zsh, counted separately from external commands.
+pub const SHELL_BUILTINS: &[&str] = &[
+    ".", "alias", "bg", "cd", "echo", "eval", "exec", "exit", "export", "fg", "history", "jobs",
+    "kill", "popd", "printf", "pushd", "pwd", "read", "set", "source", "test", "type", "ulimit",
+    "umask", "unalias", "unset", "wait",
+];
+
+/// Commands for which `-h` means something other than help, usually human-readable sizes.
+const NON_HELP_H: &[&str] = &[
+    "df", "du", "ls", "free", "sort", "grep", "rg", "egrep", "fgrep", "ssh", "mysql", "psql",
+    "shutdown", "diff", "exa", "eza", "dust",
+];
+
+/// Whether a command asks for its usage with `--help`, or `-h` where that means help.
+pub fn asks_for_help(words: &[Vec<u8>]) -> bool {
+    let Some((cmd, args)) = words.split_first() else {
+        return false;
+    };
+    let short = !NON_HELP_H.iter().any(|x| x.as_bytes() == cmd);
+    args.iter()
+        .take_while(|x| *x != b"--")
+        .any(|x| x == b"--help" || (short && x == b"-h"))
+}
+
+/// The name of the Python environment activated by `source <env>/bin/activate` (or `.`), which is
+/// the directory containing `bin`, or by `conda activate <env>`, where no name means `base`.
+pub fn venv_activation(words: &[Vec<u8>]) -> Option<String> {
+    let (cmd, args) = words.split_first()?;
+    let mut args = args
+        .iter()
+        .filter_map(|x| std::str::from_utf8(x).ok())
+        .filter(|x| !x.starts_with('-'));
+    match cmd.as_slice() {
+        b"source" | b"." => {
+            let path = Path::new(args.next()?);
+            let script = path.file_name()?.to_str()?;
+            if !script.contains("activate") {
+                return None;
+            }
+            // `env/bin/activate`, or `env/Scripts/activate` for Windows venvs used from Git Bash
+            let bin = path.parent()?;
+            let env = match bin.file_name()?.to_str()? {
+                "bin" | "Scripts" => bin.parent()?.file_name()?,
+                _ => bin.file_name()?,
+            };
+            Some(env.to_string_lossy().into_owned())
+        }
+        b"conda" | b"mamba" | b"micromamba" => {
+            if args.next()? != "activate" {
+                return None;
+            }
+            Some(args.next().unwrap_or("base").to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Counts the editor and the files it opens. Files are grouped regardless of how they were
+/// spelled: a leading `./` is dropped and paths in the home directory start with `~/`.
+fn record_editor(state: &mut State, words: &[Vec<u8>]) {
+    let Some((editor, args)) = words.split_first() else {
+        return;
+    };
+    let Some(editor) = EDITORS.iter().find(|x| x.as_bytes() == editor) else {
+        return;
+    };
+    *state.editors.entry(editor.to_string()).or_default() += 1;
+    let home = home::home_dir().map(|x| x.to_string_lossy().into_owned());
+    for arg in args {
+        let Ok(file) = std::str::from_utf8(arg) else {
+            continue;
+        };
+        // `+42` jumps to a line in vim, nano and emacs
+        if file.is_empty() || file.starts_with(['-', '+']) {
+            continue;
+        }
+        let mut file = file.trim_start_matches("./").to_owned();
+        if let Some(rest) = home.as_deref().and_then(|x| file.strip_prefix(x)) {
+            if rest.starts_with('/') {
+                file = format!("~{rest}");
+            }
+        }
+        *state.editor_files.entry(file).or_default() += 1;
+    }
+}
+
+/// Splits a command line into words the way a POSIX shell would, removing quotes and backslash
+/// escapes. Expansions like `$VAR` or globs are left as they are.
+pub fn tokenize(input: &[u8]) -> Vec<Vec<u8>> {
+    let mut words = Vec::new();
+    let mut word = Vec::new();
+    // quoting an empty string still produces a word
+    let mut in_word = false;
+    let mut it = input.iter().copied();
+    while let Some(c) = it.next() {
+        match c {
+            b' ' | b'\t' | b'\n' => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            b'\'' => {
+                in_word = true;
+                word.extend(it.by_ref().take_while(|c| *c != b'\''));
+            }
+            b'"' => {
+                in_word = true;
+                while let Some(c) = it.next() {
+                    match c {
+                        b'"' => break,
+                        b'\\' => match it.next() {
+                            // a backslash only escapes characters that are special in double
+                            // quotes, and removes escaped line breaks
+                            Some(c @ (b'"' | b'\\' | b'$' | b'`')) => word.push(c),
+                            Some(b'\n') => {}
+                            Some(c) => word.extend([b'\\', c]),
+                            None => word.push(b'\\'),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            b'\\' => match it.next() {
+                Some(b'\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => {
+                    in_word = true;
+                    word.push(b'\\');
+                }
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// The current and the longest run of consecutive days (local time) with at least one command. A
+/// streak is still current if the last command was yesterday.
+pub fn compute_streaks(timestamps: &[u64]) -> (u64, u64) {
+    let mut days: Vec<_> = timestamps
+        .iter()
+        .filter_map(|x| time::local_time(*x))
+        .map(|x| x.date.day_number())
+        .collect();
+    days.sort_unstable();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous = None;
+    for day in &days {
+        streak = if previous == Some(day - 1) {
+            streak + 1
+        } else {
+            1
+        };
+        longest = longest.max(streak);
+        previous = Some(*day);
+    }
+    let today = time::local_time(time::now()).map(|x| x.date.day_number());
+    let current = match (previous, today) {
+        (Some(last), Some(today)) if today - last <= 1 => streak,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+/// The local calendar day with the most entries, and how many there were.
+pub fn busiest_day(timestamps: &[u64]) -> Option<(time::Date, u64)> {
+    let mut days: HashMap<time::Date, u64> = HashMap::new();
+    for local in timestamps.iter().filter_map(|x| time::local_time(*x)) {
+        *days.entry(local.date).or_default() += 1;
+    }
+    most_common(&days)
+        .first()
+        .map(|(count, date)| (**date, *count))
+}
+
+/// The number of commands on every local calendar day from the first timestamp to the last one,
+/// including the days without any.
+pub fn daily_counts(timestamps: &[u64]) -> Vec<(time::Date, u64)> {
+    let mut days: HashMap<i64, u64> = HashMap::new();
+    for local in timestamps.iter().filter_map(|x| time::local_time(*x)) {
+        *days.entry(local.date.day_number()).or_default() += 1;
+    }
+    let (Some(first), Some(last)) = (days.keys().min(), days.keys().max()) else {
+        return Vec::new();
+    };
+    (*first..=*last)
+        .map(|day| {
+            let count = days.get(&day).copied().unwrap_or(0);
+            (time::Date::from_day_number(day), count)
+        })
+        .collect()
+}
+
+/// The commands of [`State::command_timestamps`] run on weekdays and those run on weekends,
+/// Saturday and Sunday in local time, counted by their first word.
+pub fn compute_weekday_weekend(
+    command_timestamps: &[(String, u64)],
+) -> (HashMap<&str, u32>, HashMap<&str, u32>) {
+    let (mut weekday, mut weekend) = (HashMap::new(), HashMap::new());
+    for (label, timestamp) in command_timestamps {
+        let Some(local) = time::local_time(*timestamp) else {
+            continue;
+        };
+        let cmd = label.split(' ').next().unwrap_or(label);
+        let counts: &mut HashMap<&str, u32> = match local.weekday {
+            5 | 6 => &mut weekend,
+            _ => &mut weekday,
+        };
+        *counts.entry(cmd).or_default() += 1;
+    }
+    (weekday, weekend)
+}
+
+/// The number of commands in each hour that had any, as the timestamp the hour starts at and the
+/// count, oldest first. The hour that is still going on is left out. Hours start on the full hour
+/// in UTC, which is also the full hour locally except in the few timezones offset by a fraction.
+pub fn hourly_buckets(timestamps: &[u64]) -> Vec<(u64, u64)> {
+    let current = time::now() / 3600;
+    let mut hours: HashMap<u64, u64> = HashMap::new();
+    for hour in timestamps.iter().map(|x| x / 3600).filter(|x| *x < current) {
+        *hours.entry(hour).or_default() += 1;
+    }
+    let mut hours: Vec<_> = hours
+        .into_iter()
+        .map(|(hour, count)| (hour * 3600, count))
+        .collect();
+    hours.sort_unstable();
+    hours
+}
+
+/// The average number of commands per day of [`State::days`].
+pub fn average_commands_per_day(state: &State) -> Option<f64> {
+    Some(state.total_commands as f64 / state.days()? as f64)
+}
+
+/// Splits a command line at the unquoted `|`s of a pipeline. `||` is left alone.
+pub fn split_pipeline(input: &[u8]) -> Vec<&[u8]> {
+    let mut stages = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut i = 0;
+    while i < input.len() {
+        match (input[i], quote) {
+            (b'\\', q) if q != Some(b'\'') => i += 1,
+            (c @ (b'\'' | b'"'), None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (b'|', None) if input.get(i + 1) == Some(&b'|') => i += 1,
+            (b'|', None) => {
+                stages.push(&input[start..i]);
+                // `|&` pipes stderr as well
+                if input.get(i + 1) == Some(&b'&') {
+                    i += 1;
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    stages.push(&input[start.min(input.len())..]);
+    stages
+}
+
+/// Whether `word` is a `NAME=value` assignment, `NAME` being a shell identifier.
+pub fn is_env_assignment(word: &[u8]) -> bool {
+    match word
+        .iter()
+        .position(|x| !(x.is_ascii_alphanumeric() || *x == b'_'))
+    {
+        Some(i) => i > 0 && word[i] == b'=' && !word[0].is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Drops the `VAR=value` assignments in front of a command.
+pub fn strip_env_vars(words: &mut Vec<Vec<u8>>) {
+    let env_vars = words.iter().take_while(|x| is_env_assignment(x)).count();
+    words.drain(..env_vars);
+}
+
+/// Drops a leading `nohup` and a trailing `&`, returning whether there was either, i.e. whether
+/// the command was run in the background. `&&` is not a `&`.
+pub fn strip_background(words: &mut Vec<Vec<u8>>) -> bool {
+    let mut background = false;
+    if words.first().is_some_and(|x| x == b"nohup") {
+        words.remove(0);
+        background = true;
+    }
+    if let Some(last) = words.last_mut() {
+        if last.ends_with(b"&") && !last.ends_with(b"&&") {
+            last.pop();
+            if last.is_empty() {
+                words.pop();
+            }
+            background = true;
+        }
+    }
+    background
+}
+
+/// Drops a leading `sudo` or `doas` together with its options, returning whether there was one.
+pub fn strip_sudo(words: &mut Vec<Vec<u8>>) -> bool {
+    let Some(b"sudo" | b"doas") = words.first().map(Vec::as_slice) else {
+        return false;
+    };
+    let mut i = 1;
+    while let Some(word) = words.get(i) {
+        match word.as_slice() {
+            b"--" => {
+                i += 1;
+                break;
+            }
+            // options that take the next word as their value
+            b"-u" | b"-g" | b"-C" | b"-D" | b"-p" | b"-r" | b"-t" | b"-T" | b"-U" | b"--user"
+            | b"--group" | b"--close-from" | b"--chdir" | b"--prompt" | b"--role" | b"--type"
+            | b"--command-timeout" | b"--other-user" => i += 2,
+            x if x.starts_with(b"-") => i += 1,
+            _ => break,
+        }
+    }
+    words.drain(..i.min(words.len()));
+    true
+}
+
+/// How fast the weight of a command run decays by default in [`weighted_ranking`], per day.
+pub const RECENCY_DECAY: f64 = 0.01;
+
+/// Ranks commands by how often they were run, with each run weighted by `e^(-lambda * age)` where
+/// the age is in days before `now`. This surfaces recently adopted tools that do not have large
+/// counts yet. Entries are `(command, timestamp)`; the result is highest score first.
+pub fn weighted_ranking(entries: &[(String, u64)], now: u64, lambda: f64) -> Vec<(f64, String)> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for (cmd, timestamp) in entries {
+        let age_days = now.saturating_sub(*timestamp) as f64 / (24.0 * 60.0 * 60.0);
+        *scores.entry(cmd).or_default() += (-lambda * age_days).exp();
+    }
+    let mut ranking: Vec<_> = scores
+        .into_iter()
+        .map(|(cmd, score)| (score, cmd.to_owned()))
+        .collect();
+    ranking.sort_unstable_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    ranking
+}
+
+/// Seconds without a command after which a new session starts, unless the config says otherwise.
+pub const SESSION_GAP: u64 = 30 * 60;
+
+/// A stretch of commands without a long break in between, see [`compute_sessions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub start: u64,
+    pub end: u64,
+    pub commands: usize,
+}
+
+impl Session {
+    /// Seconds from the first to the last command.
+    pub fn duration(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// Groups the timestamps into sessions, starting a new one wherever there are more than `gap`
+/// seconds between two commands. The timestamps do not have to be sorted.
+pub fn compute_sessions(timestamps: &[u64], gap: u64) -> Vec<Session> {
+    let mut timestamps = timestamps.to_vec();
+    timestamps.sort_unstable();
+    let mut sessions: Vec<Session> = Vec::new();
+    for timestamp in timestamps {
+        match sessions.last_mut() {
+            Some(session) if timestamp - session.end <= gap => {
+                session.end = timestamp;
+                session.commands += 1;
+            }
+            _ => sessions.push(Session {
+                start: timestamp,
+                end: timestamp,
+                commands: 1,
+            }),
+        }
+    }
+    sessions
+}
+
+/// Counts all entries of `command_history` into `state`, expanding the aliases from `config`.
+pub fn process_command_history<H: CommandHistory + ?Sized>(
+    state: &mut State,
+    command_history: &mut H,
+    config: &Config,
+) {
+    // the command the current session went on with, and when
+    let mut previous: Option<(String, Option<u64>)> = None;
+    let session_gap = config.session_gap();
+    // the working directory as followed through the `cd`s, unknown until the first absolute one,
+    // and the one before it for `cd -`
+    let mut current_dir: Option<String> = None;
+    let mut previous_dir: Option<String> = None;
+    // from the most recent `# session: <name>` line
+    let mut session_name: Option<String> = None;
+    for entry in command_history {
+        if config.privacy_filter.matches(&entry.command) {
+            continue;
+        }
+        if let Some(name) = session_marker(&entry.command) {
+            session_name = Some(name.to_owned());
+            continue;
+        }
+        if let Some(name) = &session_name {
+            *state.sessions_by_name.entry(name.clone()).or_default() += 1;
+        }
+        if let Some(timestamp) = entry.timestamp {
+            extend_span(&mut state.time_span, timestamp, timestamp);
+            state.entry_timestamps.push(timestamp);
+            if let Some(local) = time::local_time(timestamp) {
+                state.hourly_counts[local.hour as usize] += 1;
+                state.weekday_counts[local.weekday as usize] += 1;
+                *state
+                    .weekly_counts
+                    .entry(local.date.iso_week())
+                    .or_default() += 1;
+            }
+        }
+        if state
+            .longest_command
+            .as_ref()
+            .is_none_or(|x| entry.command.len() > x.1)
+        {
+            state.longest_command = Some((entry.command.clone(), entry.command.len()));
+        }
+        let mut words = tokenize(&entry.command);
+        strip_env_vars(&mut words);
+        let background = strip_background(&mut words);
+        if background {
+            state.background_jobs += 1;
+        }
+        let stages: Vec<_> = split_pipeline(&entry.command)
+            .into_iter()
+            .filter(|x| !x.trim_ascii().is_empty())
+            .collect();
+        if !stages.is_empty() {
+            state.total_commands += 1;
+            let depth = u8::try_from(stages.len()).unwrap_or(u8::MAX);
+            *state.pipeline_depth_histogram.entry(depth).or_default() += 1;
+        }
+        // the command whose output goes into the next stage, without the subcommand of its label
+        let mut piped_from: Option<String> = None;
+        for (i, stage) in stages.into_iter().enumerate() {
+            // the time a pipeline took is attributed to the command it starts with
+            let elapsed = entry.elapsed.filter(|_| i == 0);
+            let label = process_invocation(state, stage, entry.timestamp, elapsed, config);
+            let command = label.as_deref().and_then(|x| x.split(' ').next());
+            if let (Some(from), Some(to)) = (piped_from.take(), command) {
+                *state
+                    .pipe_transitions
+                    .entry((from, to.to_owned()))
+                    .or_default() += 1;
+            }
+            piped_from = command.map(str::to_owned);
+            // a sequence is made up of whole command lines, so only the first stage counts
+            if i > 0 {
+                continue;
+            }
+            let Some(label) = label else {
+                continue;
+            };
+            if let [cmd, rest @ ..] = words.as_slice() {
+                if cmd == b"cd" || cmd == b"pushd" {
+                    let target = rest.first().map(Vec::as_slice);
+                    let next =
+                        change_directory(current_dir.as_deref(), previous_dir.as_deref(), target);
+                    previous_dir = std::mem::replace(&mut current_dir, next);
+                } else if let Some(dir) = &current_dir {
+                    *state
+                        .commands_by_directory
+                        .entry(dir.clone())
+                        .or_default()
+                        .entry(label.clone())
+                        .or_default() += 1;
+                }
+            }
+            if background {
+                *state
+                    .backgrounded_commands
+                    .entry(label.clone())
+                    .or_default() += 1;
+            }
+            if let Some((prev, prev_timestamp)) = previous.take() {
+                let same_session = match (prev_timestamp, entry.timestamp) {
+                    (Some(a), Some(b)) => b.abs_diff(a) <= session_gap,
+                    _ => true,
+                };
+                if same_session {
+                    *state
+                        .command_bigrams
+                        .entry((prev, label.clone()))
+                        .or_default() += 1;
+                }
+            }
+            previous = Some((label, entry.timestamp));
+        }
+    }
+    detect_typos(state);
+    state.danger_ratio = compute_danger_ratio(state);
+}
+
+/// The section (0 if none was given) and page of a `man` invocation.
+pub fn man_page(tokens: &[&[u8]]) -> Option<(u8, String)> {
+    let (section, page) = match tokens {
+        [b"man", section, page, ..] if section.iter().all(u8::is_ascii_digit) => {
+            (std::str::from_utf8(section).ok()?.parse().ok()?, page)
+        }
+        // just a section number is not a page
+        [b"man", page, ..] if !page.iter().all(u8::is_ascii_digit) => (0, page),
+        _ => return None,
+    };
+    Some((section, String::from_utf8(page.to_vec()).ok()?))
+}
+
+/// The git subcommand of a `git` or `g` invocation, or of a shell alias for one.
+pub fn git_subcommand(tokens: &[&[u8]], git_aliases: &HashMap<String, String>) -> Option<String> {
+    match tokens {
+        [b"g" | b"git", subcommand, ..] => String::from_utf8(subcommand.to_vec()).ok(),
+        [alias, ..] => git_alias(git_aliases, std::str::from_utf8(alias).ok()?).map(str::to_owned),
+        [] => None,
+    }
+}
+
+/// The subcommands cargo itself accepts abbreviated.
+const CARGO_BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("b", "build"),
+    ("c", "check"),
+    ("d", "doc"),
+    ("r", "run"),
+    ("rm", "remove"),
+    ("t", "test"),
+];
+
+/// The subcommand of a cargo invocation, or of a shell alias for one from `cargo_aliases`.
+/// Toolchain overrides like `+nightly` and global flags before the subcommand are skipped.
+pub fn cargo_subcommand(
+    tokens: &[&[u8]],
+    cargo_aliases: &HashMap<String, String>,
+) -> Option<String> {
+    let (cmd, args) = tokens.split_first()?;
+    let cmd = std::str::from_utf8(cmd).ok()?;
+    if cmd != "cargo" {
+        return cargo_aliases.get(cmd).cloned();
+    }
+    let subcommand = args
+        .iter()
+        .find(|x| !x.starts_with(b"+") && !x.starts_with(b"-"))?;
+    let subcommand = std::str::from_utf8(subcommand).ok()?;
+    let subcommand = CARGO_BUILTIN_ALIASES
+        .iter()
+        .find(|x| x.0 == subcommand)
+        .map_or(subcommand, |x| x.1);
+    Some(subcommand.to_owned())
+}
+
+/// How often two commands have to follow each other before an alias for them is suggested.
+const ALIAS_SUGGESTION_THRESHOLD: u32 = 10;
+
+/// Aliases for the command sequences of [`State::command_bigrams`] that were run more than
+/// [`ALIAS_SUGGESTION_THRESHOLD`] times, as `(name, expansion, count)`, most frequent first. The
+/// name is made of the initials of the words, e.g. `gac` for `git add && git commit`. Sequences
+/// that already have an alias are left out, as are names that are taken by an alias or command.
+/// The sequences only know the commands and git subcommands, so arguments have to be added by
+/// hand.
+pub fn suggest_aliases(state: &State, config: &Config) -> Vec<(String, String, u32)> {
+    let mut suggestions = Vec::new();
+    for (count, (first, second)) in most_common(&state.command_bigrams) {
+        if count <= ALIAS_SUGGESTION_THRESHOLD || first == second {
+            continue;
+        }
+        let expansion = format!("{first} && {second}");
+        let taken = |x: &str| {
+            config.aliases.contains_key(x)
+                || config.shell_aliases.contains(x)
+                || state.commands.contains_key(x)
+                || suggestions.iter().any(|(name, _, _)| name == x)
+        };
+        let aliased = config
+            .aliases
+            .values()
+            .map(String::as_str)
+            .chain(config.shell_aliases.expansions())
+            .any(|x| x == expansion || x == format!("{first}; {second}"));
+        // commands run in turns would also be suggested the other way around
+        let reversed = format!("{second} && {first}");
+        if aliased || suggestions.iter().any(|x| x.1 == reversed) {
+            continue;
+        }
+        // the command is only repeated in the name if the second one is a different one
+        let first_words: Vec<&str> = first.split(' ').collect();
+        let mut second_words: Vec<&str> = second.split(' ').collect();
+        if second_words.len() > 1 && second_words[0] == first_words[0] {
+            second_words.remove(0);
+        }
+        let name: String = first_words
+            .iter()
+            .chain(&second_words)
+            .filter_map(|x| x.chars().find(char::is_ascii_alphanumeric))
+            .collect();
+        if name.len() < 2 || taken(&name) {
+            continue;
+        }
+        suggestions.push((name, expansion, count));
+    }
+    suggestions
+}
+
+/// Counts a single command, i.e. one stage of a pipeline. Returns how it is shown in
+/// [`State::command_bigrams`].
+fn process_invocation(
+    state: &mut State,
+    command: &[u8],
+    timestamp: Option<u64>,
+    elapsed: Option<Duration>,
+    config: &Config,
+) -> Option<String> {
+    let mut words = tokenize(command);
+    strip_env_vars(&mut words);
+    if let Some(expansion) = words
+        .first()
+        .and_then(|x| std::str::from_utf8(x).ok())
+        .and_then(|x| config.aliases.get(x))
+    {
+        words.splice(..1, tokenize(expansion.as_bytes()));
+    }
+    config.shell_aliases.expand(&mut words);
+    if strip_sudo(&mut words) {
+        state.sudo_count += 1;
+        strip_env_vars(&mut words);
+    }
+    strip_background(&mut words);
+    if let Some(op) = package_manager_op(&words) {
+        *state.package_manager_ops.entry(op).or_default() += 1;
+    }
+    if let Some(op) = docker_op(&words) {
+        *state.docker_ops.entry(op).or_default() += 1;
+    }
+    if let Some(op) = k8s_op(&words) {
+        *state.k8s_ops.entry(op).or_default() += 1;
+    }
+    if let Some(op) = npm_op(&words) {
+        *state.npm_ops.entry(op).or_default() += 1;
+    }
+    for op in systemctl_ops(&words) {
+        *state.systemctl_ops.entry(op).or_default() += 1;
+    }
+    if is_undo(&words) {
+        state.undo_count += 1;
+    }
+    if is_destructive(&words) {
+        state.destructive_commands += 1;
+    }
+    if let Some(filter) = jq_filter(&words).filter(|x| !x.is_empty()) {
+        let bucket = filter.len().ilog2() as u8;
+        *state.jq_filter_lengths.entry(bucket).or_default() += 1;
+        record_jq_filter(state, filter.to_owned());
+    }
+    if let Some(op) = terraform_op(&words) {
+        *state.terraform_ops.entry(op).or_default() += 1;
+    }
+    if let Some(subcommand) = tmux_subcommand(&words) {
+        *state.tmux_subcommands.entry(subcommand).or_default() += 1;
+    }
+    if let Some(op) = tar_op(&words) {
+        *state.tar_ops.entry(op).or_default() += 1;
+    }
+    if let Some(op) = nix_op(&words) {
+        *state.nix_ops.entry(op).or_default() += 1;
+        if words[1..].iter().any(|x| x.contains(&b'#')) {
+            state.nix_flake_commands += 1;
+        }
+    }
+    if let Some(op) = ansible_op(&words) {
+        *state.ansible_ops.entry(op).or_default() += 1;
+    }
+    if let Some(op) = cloud_op(&words) {
+        *state.cloud_ops.entry(op).or_default() += 1;
+    }
+    for target in build_targets(&words) {
+        *state.build_targets.entry(target).or_default() += 1;
+    }
+    if let Some((tool, patterns)) = search(&words) {
+        *state.search_tools.entry(tool.to_owned()).or_default() += 1;
+        for pattern in patterns {
+            *state.search_patterns.entry(pattern).or_default() += 1;
+        }
+    }
+    for host in http_targets(&words) {
+        *state.http_targets.entry(host).or_default() += 1;
+    }
+    for host in ssh_hosts(&words) {
+        *state.ssh_hosts.entry(host).or_default() += 1;
+    }
+    if asks_for_help(&words) {
+        if let Some(cmd) = words.first().and_then(|x| std::str::from_utf8(x).ok()) {
+            *state.help_sought.entry(cmd.to_owned()).or_default() += 1;
+        }
+    }
+    if let Some(env) = venv_activation(&words) {
+        *state.venv_activations.entry(env).or_default() += 1;
+    }
+    record_directory(state, &words);
+    record_args(state, &words);
+    for word in words.iter().skip(1).filter_map(|x| argument_word(x)) {
+        *state.argument_words.entry(word.to_owned()).or_default() += 1;
+    }
+    record_editor(state, &words);
+    if let Some(cmd) = words.first().and_then(|x| std::str::from_utf8(x).ok()) {
+        let args = words.len() - 1;
+        if state.most_args_command.as_ref().is_none_or(|x| args > x.1) {
+            state.most_args_command = Some((cmd.to_owned(), args));
+        }
+    }
+    let cmd = words.first().map(Vec::as_slice);
+    let mut label = cmd.and_then(|x| String::from_utf8(x.to_owned()).ok());
+    let tokens: Vec<&[u8]> = words.iter().map(Vec::as_slice).collect();
+    if let Some(page) = man_page(&tokens) {
+        *state.man_pages.entry(page).or_default() += 1;
+    }
+    if let Some(subcommand) = git_subcommand(&tokens, &config.git_aliases) {
+        label = Some(format!("git {subcommand}"));
+        *state.git_subcommands.entry(subcommand).or_default() += 1;
+    }
+    for flag in git_flags(&tokens) {
+        *state.git_flags.entry(flag.to_owned()).or_default() += 1;
+    }
+    if let Some(subcommand) = cargo_subcommand(&tokens, &config.cargo_aliases) {
+        *state.cargo_subcommands.entry(subcommand).or_default() += 1;
+    }
+    for analyzer in &mut state.analyzers {
+        analyzer.process(&tokens, timestamp);
+    }
+    (|| {
+        if let Some(cmd) = cmd {
+            let cmd = String::from_utf8(cmd.to_owned())?;
+            if let Some(elapsed) = elapsed {
+                *state.command_durations.entry(cmd.clone()).or_default() += elapsed.as_secs();
+            }
+            if let Some(timestamp) = timestamp {
+                state.command_timestamps.push((cmd.clone(), timestamp));
+                state
+                    .commands_first_seen
+                    .entry(cmd.clone())
+                    .and_modify(|x| *x = (*x).min(timestamp))
+                    .or_insert(timestamp);
+            }
+            for category in config.categories.of(&cmd) {
+                *state
+                    .command_categories
+                    .entry(category.clone())
+                    .or_default() += 1;
+            }
+            if SHELL_BUILTINS.contains(&cmd.as_str()) {
+                *state.builtins.entry(cmd.clone()).or_default() += 1;
+            }
+            *state.commands.entry(cmd).or_default() += 1;
+        };
+
+        Result::<(), Box<dyn std::error::Error>>::Ok(())
+    })()
+    .ok();
+    label
+}
+
+/// Whether the characters of `pattern` appear in `text` in order, ignoring case.
+pub fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|x| text.any(|y| x == y))
+}
+
+/// The history files found on this machine, together with their paths.
+pub fn history_sources(mmap: bool) -> Vec<(PathBuf, History)> {
+    fn open<H>(
+        path: Option<PathBuf>,
+        open: impl FnOnce(&Path) -> Option<H>,
+        variant: fn(H) -> History,
+    ) -> Option<(PathBuf, History)> {
+        let path = path?;
+        let history = open(&path)?;
+        Some((path, variant(history)))
+    }
+
+    [
+        open(
+            ZshHistory::path(),
+            |path| ZshHistory::open_range(path, 0, u64::MAX, mmap),
+            History::Zsh,
+        ),
+        open(BashHistory::path(), BashHistory::open, History::Bash),
+        open(FishHistory::path(), FishHistory::open, History::Fish),
+        open(TcshHistory::path(), TcshHistory::open, History::Tcsh),
+        open(XonshHistory::path(), XonshHistory::open, History::Xonsh),
+        open(
+            NushellHistory::path(),
+            NushellHistory::open,
+            History::Nushell,
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The shells whose history files can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    Zsh,
+    Bash,
+    Fish,
+    Nushell,
+    Tcsh,
+    Xonsh,
+}
+
+impl HistoryFormat {
+    /// In the order [`history_sources`] reads them.
+    pub const ALL: [Self; 6] = [
+        Self::Zsh,
+        Self::Bash,
+        Self::Fish,
+        Self::Tcsh,
+        Self::Xonsh,
+        Self::Nushell,
+    ];
+
+    /// Where the shell keeps its history by default.
+    pub fn default_path(self) -> Option<PathBuf> {
+        match self {
+            Self::Zsh => ZshHistory::path(),
+            Self::Bash => BashHistory::path(),
+            Self::Fish => FishHistory::path(),
+            Self::Nushell => NushellHistory::path(),
+            Self::Tcsh => TcshHistory::path(),
+            Self::Xonsh => XonshHistory::path(),
+        }
+    }
+
+    /// Tells the formats apart by the first line of the file, or the SQLite header of a Nushell
+    /// database. A directory is taken to hold xonsh session files, as does a `.json` file.
+    pub fn detect(path: &Path) -> std::io::Result<Self> {
+        if path.is_dir() || path.extension().is_some_and(|x| x == "json") {
+            return Ok(Self::Xonsh);
+        }
+        let first_line = BufReader::new(compress::open(path)?)
+            .split(b'\n')
+            .find(|x| x.as_ref().map_or(true, |x| !x.is_empty()))
+            .transpose()?
+            .unwrap_or_default();
+        Ok(if first_line.starts_with(b"SQLite format 3\0") {
+            Self::Nushell
+        } else if TcshHistory::parse_timestamp(&first_line).is_some() {
+            Self::Tcsh
+        } else if first_line.starts_with(b"- cmd:") {
+            Self::Fish
+        } else if first_line.starts_with(b": ") && first_line.contains(&b';') {
+            Self::Zsh
+        } else {
+            Self::Bash
+        })
+    }
+}
+
+/// Opens a history file of any supported shell, see [`HistoryFormat::detect`].
+/// `mmap` is passed on to [`ZshHistory::open_range`].
+pub fn open_history_file(path: &Path, mmap: bool) -> std::io::Result<History> {
+    let history = match HistoryFormat::detect(path)? {
+        HistoryFormat::Fish => FishHistory::open(path).map(History::Fish),
+        HistoryFormat::Zsh => ZshHistory::open_range(path, 0, u64::MAX, mmap).map(History::Zsh),
+        HistoryFormat::Bash => BashHistory::open(path).map(History::Bash),
+        HistoryFormat::Nushell => NushellHistory::open(path).map(History::Nushell),
+        HistoryFormat::Tcsh => TcshHistory::open(path).map(History::Tcsh),
+        HistoryFormat::Xonsh => XonshHistory::open(path).map(History::Xonsh),
+    };
+    history.ok_or_else(|| std::io::Error::other("could not open the history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(lines: &[&str]) -> State {
+        let mut history = MockHistory::new(lines.iter().map(|x| x.as_bytes().to_vec()).collect());
+        let mut state = State::default();
+        process_command_history(&mut state, &mut history, &Config::default());
+        state
+    }
+
+    fn counts<K: Ord + Clone>(map: &HashMap<K, u32>) -> Vec<(K, u32)> {
+        let mut entries: Vec<_> = map.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        entries.sort();
+        entries
+    }
+
+    fn strings(entries: &[(&str, u32)]) -> Vec<(String, u32)> {
+        entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn plain_commands() {
+        let state = process(&["ls", "ls -la", "cargo build", "ls /tmp"]);
+        assert_eq!(counts(&state.commands), strings(&[("cargo", 1), ("ls", 3)]));
+        assert_eq!(state.total_commands, 4);
+        assert!(state.git_subcommands.is_empty());
+        assert!(state.man_pages.is_empty());
+    }
+
+    #[test]
+    fn man_pages() {
+        let state = process(&["man ls", "man 3 printf", "man 1 ls", "man ls", "man 5"]);
+        assert_eq!(
+            counts(&state.man_pages),
+            vec![
+                ((0, "ls".to_owned()), 2),
+                ((1, "ls".to_owned()), 1),
+                ((3, "printf".to_owned()), 1),
+            ]
+        );
+        // a bare section number is not a page, but still a use of man
+        assert_eq!(counts(&state.commands), strings(&[("man", 5)]));
+    }
+
+    #[test]
+    fn git_subcommands() {
+        let state = process(&["git status", "git commit -m 'x'", "g push", "git"]);
+        assert_eq!(
+            counts(&state.git_subcommands),
+            strings(&[("commit", 1), ("push", 1), ("status", 1)])
+        );
+        assert_eq!(counts(&state.commands), strings(&[("g", 1), ("git", 3)]));
+    }
+
+    #[test]
+    fn git_aliases() {
+        let state = process(&["gc", "gc -m 'x'", "ga .", "gca", "gau", "gcb"]);
+        assert_eq!(
+            counts(&state.git_subcommands),
+            strings(&[("add", 2), ("checkout", 1), ("commit", 3)])
+        );
+        // the alias is what was typed, so that is the command that gets counted
+        assert_eq!(
+            counts(&state.commands),
+            strings(&[("ga", 1), ("gau", 1), ("gc", 2), ("gca", 1), ("gcb", 1)])
+        );
+    }
+
+    #[test]
+    fn env_var_prefixes() {
+        let state = process(&[
+            "RUST_LOG=debug cargo run",
+            "A=1 B='2 3' git status",
+            "FOO=bar",
+        ]);
+        assert_eq!(
+            counts(&state.commands),
+            strings(&[("cargo", 1), ("git", 1)])
+        );
+        assert_eq!(counts(&state.git_subcommands), strings(&[("status", 1)]));
+    }
+
+    #[test]
+    fn analyzers_see_every_command() {
+        /// Lists the first word of every command.
+        struct FirstWords(Vec<String>);
+
+        impl Analyzer for FirstWords {
+            fn process(&mut self, tokens: &[&[u8]], _timestamp: Option<u64>) {
+                self.0.push(String::from_utf8_lossy(tokens[0]).into_owned());
+            }
+
+            fn report(&self, _limit: usize) -> Vec<analyzer::Section> {
+                vec![analyzer::Section {
+                    heading: "First words:".into(),
+                    column: "Word",
+                    rows: Vec::new(),
+                    lines: vec![self.0.join(" ")],
+                }]
+            }
+        }
+
+        let mut history =
+            MockHistory::new(vec![b"ls | grep x".to_vec(), b"sudo man 3 printf".to_vec()]);
+        let mut state = State::default();
+        state.analyzers.push(Box::new(FirstWords(Vec::new())));
+        process_command_history(&mut state, &mut history, &Config::default());
+        assert_eq!(state.analyzers[0].report(10)[0].lines, ["ls grep man"]);
+    }
+
+    #[test]
+    fn analyzers_are_merged() {
+        let podman = |line: &[u8]| {
+            let mut state = State::default();
+            state
+                .analyzers
+                .push(Box::new(analyzer::PodmanAnalyzer::default()));
+            let mut history = MockHistory::new(vec![line.to_vec()]);
+            process_command_history(&mut state, &mut history, &Config::default());
+            state
+        };
+        let state = merge_states(podman(b"podman pull alpine"), podman(b"podman pull alpine"));
+        assert_eq!(state.analyzers.len(), 1);
+        let rows: Vec<_> = state.analyzers[0]
+            .report(10)
+            .into_iter()
+            .map(|x| (x.heading, x.rows))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (
+                    "Your top Podman subcommands:".to_owned(),
+                    vec![(2, "podman pull".to_owned())]
+                ),
+                (
+                    "Your top Podman images:".to_owned(),
+                    vec![(2, "alpine".to_owned())]
+                ),
+            ]
+        );
+        // and saved along with the state
+        let mut bytes = Vec::new();
+        state.encode(&mut bytes);
+        let loaded = State::decode(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            loaded.analyzers[0].report(10),
+            state.analyzers[0].report(10)
+        );
+    }
+
+    #[test]
+    fn commands_by_directory() {
+        let state = process(&[
+            "ls",
+            "cd /srv/app",
+            "make",
+            "cd ./src/../docs",
+            "ls",
+            "cd -",
+            "ls",
+        ]);
+        let mut directories: Vec<_> = state
+            .commands_by_directory
+            .iter()
+            .map(|(dir, commands)| (dir.as_str(), counts(commands)))
+            .collect();
+        directories.sort();
+        assert_eq!(
+            directories,
+            [
+                ("/srv/app", strings(&[("ls", 1), ("make", 1)])),
+                ("/srv/app/docs", strings(&[("ls", 1)])),
+            ]
+        );
+    }
+
+    #[test]
+    fn persist_cursor_survives_trimming() {
+        use persist::Cursor;
+        let entries = |lines: &[(&str, Option<u64>)]| {
+            lines
+                .iter()
+                .map(|(command, timestamp)| HistoryEntry {
+                    command: command.as_bytes().to_vec(),
+                    timestamp: *timestamp,
+                    elapsed: None,
+                    exit_status: None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let commands =
+            |x: Vec<HistoryEntry>| -> Vec<Vec<u8>> { x.into_iter().map(|x| x.command).collect() };
+
+        let (_, cursor) = Cursor::unseen(None, entries(&[("a", None), ("b", None), ("c", None)]));
+        // the oldest entry was dropped when a new one was added
+        let (new, cursor) = Cursor::unseen(
+            cursor.as_ref(),
+            entries(&[("b", None), ("c", None), ("d", None)]),
+        );
+        assert_eq!(commands(new), [b"d"]);
+        assert_eq!(cursor, Some(Cursor::Position(3, Some(b"d".to_vec()))));
+
+        let (_, cursor) = Cursor::unseen(None, entries(&[("a", Some(1)), ("b", Some(2))]));
+        let (new, cursor) = Cursor::unseen(
+            cursor.as_ref(),
+            entries(&[("b", Some(2)), ("c", Some(2)), ("d", None)]),
+        );
+        assert_eq!(commands(new), [b"c", b"d"]);
+        assert_eq!(cursor, Some(Cursor::Timestamp(2, 3)));
+    }
+
+    #[test]
+    fn pipe_transitions() {
+        let state = process(&["git log | grep fix | wc -l", "grep x y | wc", "ls"]);
+        let pair = |a: &str, b: &str| (a.to_owned(), b.to_owned());
+        assert_eq!(
+            counts(&state.pipe_transitions),
+            [(pair("git", "grep"), 1), (pair("grep", "wc"), 2)]
+        );
+    }
+
+    #[test]
+    fn blank_lines() {
+        let state = process(&["", "   ", "\t", "ls", ""]);
+        assert_eq!(counts(&state.commands), strings(&[("ls", 1)]));
+        assert_eq!(state.total_commands, 1);
+    }
+
+    #[test]
+    fn zsh_multi_line_commands() {
+        let path = std::env::temp_dir().join(format!("zsh_history_{}", std::process::id()));
+        std::fs::write(
+            &path,
+            ": 1700000000:0;for i in 1 2; do\\\n: echo $i\\\ndone\n: 1700000001:0;ls\n",
+        )
+        .unwrap();
+        for mmap in [false, true] {
+            let commands: Vec<_> = ZshHistory::open_range(&path, 0, u64::MAX, mmap)
+                .unwrap()
+                .map(|x| String::from_utf8(x.command).unwrap())
+                .collect();
+            assert_eq!(commands, ["for i in 1 2; do\n: echo $i\ndone", "ls"]);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn templates() {
+        let template =
+            template::Template::parse("{rank}. {command} {{{count}}} {percent}%").unwrap();
+        let row = template::Row {
+            command: "ls",
+            count: 3,
+            rank: 1,
+            total: 8,
+        };
+        assert_eq!(template.render(&row), "1. ls {3} 37.5%");
+        assert!(template::Template::parse("{cmd}").is_err());
+        assert!(template::Template::parse("{command").is_err());
+        assert!(template::Template::parse("}").is_err());
+    }
+
+    #[test]
+    fn diff_states_between_histories() {
+        let a = process(&["ls", "ls", "git status", "vim x", "htop"]);
+        let b = process(&["ls", "git status", "git push", "git log", "hx x", "htop"]);
+        assert_eq!(
+            diff_states(&a, &b),
+            StateDiff {
+                added: strings(&[("hx", 1)]),
+                removed: strings(&[("vim", 1)]),
+                changed: vec![("git".into(), 1, 3), ("ls".into(), 2, 1)],
+            }
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let text = "tab\t \"quoted\" caf\u{e9} \u{1f600} \\ \u{1}".to_owned();
+        let parsed = json::parse(vec![(text.clone(), 3u32)].to_json().as_bytes()).unwrap();
+        assert_eq!(
+            parsed,
+            json::Value::Array(vec![json::Value::Array(vec![
+                json::Value::String(text),
+                json::Value::Number(3.0),
+            ])])
+        );
+        let parsed = json::parse(br#"{"a": [true, null, -1.5e3], "b": "\ud83d\ude00"}"#).unwrap();
+        assert_eq!(
+            parsed.get("b").and_then(json::Value::as_str),
+            Some("\u{1f600}")
+        );
+        assert_eq!(
+            parsed.get("a").and_then(|x| x.as_array()).map(|x| x.len()),
+            Some(3)
+        );
+        assert!(json::parse(b"[1, 2").is_err());
+    }
+
+    #[test]
+    fn anonymized_words() {
+        let cases = [
+            ("https://api.example.com/v1", Some("<url>")),
+            ("git@github.com:me/repo.git", Some("<url>")),
+            ("alice@example.org", Some("<email>")),
+            ("123e4567-e89b-12d3-a456-426614174000", Some("<uuid>")),
+            ("root@db1", Some("<host>")),
+            ("web1.corp.internal", Some("<host>")),
+            ("10.0.0.1:22", Some("<host>")),
+            ("~/notes.md", Some("<path>")),
+            ("/etc/hosts", Some("<path>")),
+            ("main.rs", None),
+            ("--force", None),
+            ("v1.2.3", None),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(anonymize::placeholder(word), expected, "{word}");
+        }
+        assert_eq!(
+            anonymize::anonymize("ssh -p 22 --host=a.example.com x"),
+            "ssh -p 22 --host=<host> x"
+        );
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let cases = [
+            ("pass *", "pass show email", true),
+            ("pass *", "passwd", false),
+            ("*TOKEN=*", "GH_TOKEN=abc gh pr list", true),
+            ("gpg", "gpg --list-keys", false),
+            ("a?c", "abc", true),
+            ("*[0-9]", "vim file2", true),
+            ("*[!0-9]", "vim file2", false),
+            ("[]x]", "]", true),
+            ("[abc", "[abc", true),
+            ("**b*", "aaab", true),
+            ("*a*b", "xaxxbxb", true),
+            ("", "", true),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(
+                filter::glob_match(pattern.as_bytes(), text.as_bytes()),
+                expected,
+                "{pattern} {text}"
+            );
+        }
+    }
+
+    #[test]
+    fn strip_env_vars_tricky_cases() {
+        // words are joined with `|` in the expectations to make their boundaries visible
+        let cases = [
+            ("FOO=1 make", "make"),
+            ("A=1 B=2 cargo build", "cargo|build"),
+            ("FOO=\"bar=baz\" cmd", "cmd"),
+            ("FOO='a b' cmd arg", "cmd|arg"),
+            ("_private=x cmd", "cmd"),
+            ("EMPTY= cmd", "cmd"),
+            ("PATH=$PATH:/opt/bin which foo", "which|foo"),
+            ("cmd FOO=1", "cmd|FOO=1"),
+            ("./configure --prefix=/usr", "./configure|--prefix=/usr"),
+            ("--opt=1 cmd", "--opt=1|cmd"),
+            ("=foo cmd", "=foo|cmd"),
+            ("1FOO=bar cmd", "1FOO=bar|cmd"),
+            ("FOO-BAR=1 cmd", "FOO-BAR=1|cmd"),
+            ("a.b=1 cmd", "a.b=1|cmd"),
+            ("FOO=1", ""),
+        ];
+        for (input, expected) in cases {
+            let mut words = tokenize(input.as_bytes());
+            strip_env_vars(&mut words);
+            assert_eq!(
+                String::from_utf8(words.join(&b'|')).unwrap(),
+                expected,
+                "{input}"
+            );
+        }
+    }
+}
@@ -1,179 +1,2276 @@
 use std::{
     collections::HashMap,
-    fs::File,
-    io::{BufRead, BufReader, Split},
-    iter::Peekable,
+    fmt::Display,
+    io::{IsTerminal, Write},
+    path::{Path, PathBuf},
 };
 
+use linux_wrapped::{
+    alias::AliasMap,
+    analyzer::PodmanAnalyzer,
+    anonymize, average_commands_per_day, benchmarks, busiest_day, changes_packages,
+    compute_percentiles, compute_sessions, compute_streaks, compute_weekday_weekend,
+    config::Config,
+    daily_counts, diff_states, export, fuzzy_match,
+    graph::{render_ascii_graph, sparkline},
+    history_sources, hourly_buckets, install_time,
+    json::ToJson,
+    merge_states, most_common, open_history_file, parallel,
+    persist::{Cursor, Store},
+    process_command_history, stats, suggest_aliases,
+    template::{Row, Template},
+    time, weighted_ranking, within_range, ColorChoice, History, HistoryFormat, Merge, Session,
+    State, TimeRange, ZshHistory,
+};
 use rand::seq::SliceRandom;
 
-trait CommandHistory: Iterator<Item = Vec<u8>> {}
+mod html;
+mod prometheus;
+mod tui;
+
+/// ANSI styling for the human-readable report, switched off as a whole when colors are disabled.
+#[derive(Clone, Copy)]
+struct Colorize {
+    enabled: bool,
+}
+
+impl Colorize {
+    fn new(choice: ColorChoice) -> Self {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|x| x.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        };
+        Self { enabled }
+    }
 
-impl<T: Iterator<Item = Vec<u8>>> CommandHistory for T {}
+    fn paint(self, code: &str, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
 
-struct ZshHistory {
-    lines: Peekable<Split<BufReader<File>>>,
+    fn count(self, text: impl Display) -> String {
+        self.paint("1", text)
+    }
+
+    fn name(self, text: impl Display) -> String {
+        self.paint("36", text)
+    }
+
+    fn header(self, text: impl Display) -> String {
+        self.paint("33", text)
+    }
 }
 
-impl ZshHistory {
-    fn new() -> Option<Self> {
-        let mut path = home::home_dir()?;
-        path.push(".zsh_history");
-        let f = File::open(path).ok()?;
-        let br = BufReader::new(f);
-        Some(Self {
-            lines: br.split(b'\n').peekable(),
-        })
+/// How the human-readable report is written: as colored text for the terminal, or as a Markdown
+/// document to paste elsewhere.
+#[derive(Clone, Copy)]
+enum Style {
+    Text(Colorize),
+    Markdown,
+}
+
+/// The [`Style`] of the report, and whether names in it are anonymized for `--anonymize`.
+#[derive(Clone, Copy)]
+struct Format {
+    style: Style,
+    anonymize: bool,
+}
+
+impl Format {
+    fn count(self, text: impl Display) -> String {
+        match self.style {
+            Style::Text(c) => c.count(text),
+            Style::Markdown => format!("**{text}**"),
+        }
+    }
+
+    /// `text` unstyled, with placeholders for host names, paths and the like with `--anonymize`.
+    fn plain(self, text: impl Display) -> String {
+        match self.anonymize {
+            true => anonymize::anonymize(&text.to_string()),
+            false => text.to_string(),
+        }
+    }
+
+    fn name(self, text: impl Display) -> String {
+        let text = self.plain(text);
+        match self.style {
+            Style::Text(c) => c.name(text),
+            // `|` would end a table cell
+            Style::Markdown => format!("`{}`", text.replace('|', "\\|")),
+        }
+    }
+
+    /// A host name to be passed to [`Format::name`], which with `--anonymize` becomes a
+    /// placeholder even without a dot in it.
+    fn host(self, host: &str) -> String {
+        match self.anonymize {
+            true => "<host>".into(),
+            false => host.to_owned(),
+        }
+    }
+
+    /// Starts a section.
+    fn heading(self, text: impl Display) {
+        match self.style {
+            Style::Text(c) => println!("{}", c.header(text)),
+            Style::Markdown => println!("## {}\n", text.to_string().trim_end_matches(':')),
+        }
+    }
+
+    fn line(self, text: impl Display) {
+        match self.style {
+            Style::Text(_) => println!("{text}"),
+            // consecutive lines would be joined into one paragraph
+            Style::Markdown => println!("{text}\n"),
+        }
+    }
+
+    /// Text that has to keep its line breaks and alignment, like a chart, ending in a newline.
+    fn preformatted(self, text: impl Display) {
+        match self.style {
+            Style::Text(_) => print!("{text}"),
+            Style::Markdown => println!("```\n{text}```\n"),
+        }
+    }
+
+    /// Names with their counts, one per line or as a table with the given heading for the names.
+    fn list<C: Display, N: Display>(self, column: &str, rows: impl IntoIterator<Item = (C, N)>) {
+        if let Style::Markdown = self.style {
+            println!("| {column} | Count |\n|:---|---:|");
+        }
+        for (count, name) in rows {
+            match self.style {
+                Style::Text(_) => println!("{} {}", self.count(count), self.name(name)),
+                Style::Markdown => println!("| {} | {} |", self.name(name), self.count(count)),
+            }
+        }
+        if let Style::Markdown = self.style {
+            println!();
+        }
+    }
+
+    /// Two lists of names with their counts side by side, the shorter one padded with `None`.
+    fn columns<'a>(
+        self,
+        headings: [&str; 2],
+        rows: impl IntoIterator<Item = [Option<(u32, &'a str)>; 2]>,
+    ) {
+        const WIDTH: usize = 30;
+        match self.style {
+            Style::Text(_) => println!("{:<WIDTH$} {}", headings[0], headings[1]),
+            Style::Markdown => println!(
+                "| {} | | {} | |\n|:---|---:|:---|---:|",
+                headings[0], headings[1]
+            ),
+        }
+        for row in rows {
+            match self.style {
+                Style::Text(_) => {
+                    let [left, right] = row.map(|cell| match cell {
+                        Some((count, name)) => format!("{} {}", self.count(count), self.name(name)),
+                        None => String::new(),
+                    });
+                    let padding = WIDTH.saturating_sub(visible_width(&left));
+                    println!("{left}{} {right}", " ".repeat(padding));
+                }
+                Style::Markdown => {
+                    let [left, right] = row.map(|cell| match cell {
+                        Some((count, name)) => {
+                            format!("{} | {}", self.name(name), self.count(count))
+                        }
+                        None => " | ".into(),
+                    });
+                    println!("| {left} | {right} |");
+                }
+            }
+        }
+        if let Style::Markdown = self.style {
+            println!();
+        }
+    }
+
+    /// Labelled counts with bars scaled to the largest of them.
+    fn chart(self, column: &str, rows: &[(String, u64)]) {
+        let max = rows.iter().map(|x| x.1).max().unwrap_or(0).max(1);
+        if let Style::Markdown = self.style {
+            println!("| {column} | | Count |\n|:---|:---|---:|");
+        }
+        for (label, count) in rows {
+            match self.style {
+                Style::Text(_) => println!("{label} {} {}", bar(*count, max), self.count(count)),
+                Style::Markdown => {
+                    println!("| {label} | {} | {} |", bar(*count, max), self.count(count))
+                }
+            }
+        }
+        if let Style::Markdown = self.style {
+            println!();
+        }
     }
 }
 
-impl Iterator for ZshHistory {
-    type Item = Vec<u8>;
+/// How often git can be forced before the report warns about it.
+const FORCE_WARNING: u32 = 10;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let item = self.lines.next();
-            item.as_ref()?;
-            if let Some(mut result) = item.transpose().ok().flatten().and_then(|x| {
-                if x.len() == 0 || x[0] != b':' {
-                    return None;
-                }
-                let mut it = x.split(|x| *x == b';');
-                it.next();
-                it.next().map(|x| x.to_owned())
-            }) {
-                while let Some(next) = self.lines.peek().as_ref() {
-                    if next.as_ref().unwrap().starts_with(b":") {
-                        break;
+/// Defines [`DisplayConfig`] with a field and a default for every section, which `--top-<field>`
+/// sets with the underscores written as dashes.
+macro_rules! display_config {
+    ($($(#[doc = $doc:literal])* $field:ident: $default:literal,)*) => {
+        /// How many entries each section of the report lists.
+        #[derive(Clone, Copy)]
+        struct DisplayConfig {
+            $($(#[doc = $doc])* $field: usize,)*
+        }
+
+        impl DisplayConfig {
+            /// What each section lists unless `--top` or `top_n` in the config file is given.
+            const DEFAULT: Self = Self {
+                $($field: $default,)*
+            };
+
+            /// The same number of entries for every section.
+            fn uniform(n: usize) -> Self {
+                Self {
+                    $($field: n,)*
+                }
+            }
+
+            /// The limit of the section that `--top-<section>` sets.
+            fn section(&mut self, section: &str) -> Option<&mut usize> {
+                $(if section == stringify!($field).replace('_', "-") {
+                    return Some(&mut self.$field);
+                })*
+                None
+            }
+        }
+    };
+}
+
+display_config! {
+    commands: 10,
+    git: 10,
+    git_flags: 10,
+    man: 10,
+    cargo: 5,
+    typos: 5,
+    builtins: 5,
+    /// commands ranked by how recently they were used
+    recent: 5,
+    /// the top commands on weekdays and on weekends
+    weekdays: 5,
+    /// argument words
+    words: 10,
+    /// directories with the commands run in them
+    active_directories: 3,
+    /// commands listed for each active directory
+    directory_commands: 3,
+    /// command sequences
+    bigrams: 5,
+    pipelines: 5,
+    /// commands run in the background
+    background: 3,
+    /// suggested aliases
+    aliases: 5,
+    /// package manager operations
+    packages: 10,
+    docker_images: 10,
+    /// docker subcommands
+    docker: 10,
+    /// each section of the Podman analyzer
+    podman: 10,
+    kubectl: 5,
+    npm: 5,
+    /// services managed with systemctl
+    services: 5,
+    cloud: 10,
+    terraform: 5,
+    tmux: 5,
+    /// named sessions
+    sessions: 5,
+    nix: 5,
+    ansible: 5,
+    tar: 5,
+    build_targets: 5,
+    search_patterns: 10,
+    /// hosts requested with curl and the like
+    http: 10,
+    /// commands looked up with man or --help
+    help: 5,
+    venvs: 5,
+    /// hosts connected to with ssh
+    ssh: 5,
+    /// directories visited with `cd`
+    directories: 10,
+    /// files opened in an editor
+    files: 10,
+    /// commands that took the longest
+    waits: 10,
+    /// the arguments listed by `--top-args`
+    arguments: 10,
+    /// commands listed in each part of `--compare`
+    comparison: 10,
+}
+
+#[derive(Default)]
+struct Args {
+    color: Option<ColorChoice>,
+    top: Option<usize>,
+    /// limits from `--top-<section>`, see [`DisplayConfig::section`]
+    top_sections: Vec<(String, usize)>,
+    output_json: bool,
+    /// write the report as Markdown
+    markdown: bool,
+    /// directory to write CSV files to
+    output_csv: Option<PathBuf>,
+    /// file to write the HTML report to
+    html: Option<PathBuf>,
+    /// store to accumulate statistics in across runs
+    persist: Option<PathBuf>,
+    /// only parse what was added to the zsh history since the last incremental run
+    incremental: bool,
+    /// history files to read in addition to the ones found automatically
+    history_files: Vec<PathBuf>,
+    /// file to write the computed statistics to
+    save_state: Option<PathBuf>,
+    /// statistics saved by an earlier run, read instead of the automatically found histories
+    load_state: Vec<PathBuf>,
+    range: TimeRange,
+    /// number of threads to parse the histories with
+    jobs: Option<usize>,
+    /// memory-map zsh histories instead of reading them
+    mmap: bool,
+    /// print the statistics as Prometheus metrics
+    prometheus: bool,
+    /// explore the statistics in a full-screen terminal UI
+    tui: bool,
+    /// minutes without a command after which a new session starts
+    session_gap: Option<u64>,
+    /// how fast the weight of older commands decays in the recency ranking, per day
+    recency_decay: Option<f64>,
+    /// glob patterns of commands to leave out
+    privacy_filter: Vec<String>,
+    /// leave out commands that handle secrets, see `linux_wrapped::filter::SECRET_PATTERNS`
+    exclude_secrets: bool,
+    /// seconds after which the statistics are computed and printed again
+    watch: Option<u64>,
+    /// only count what was run in this many days before now
+    recent_days: Option<u64>,
+    /// only count what was run since the system was installed, see [`install_time`]
+    since_install: bool,
+    /// print the command lines matching this, fuzzily unless `search_exact` is set
+    search: Option<String>,
+    search_exact: bool,
+    /// print a line in this format for each of the top commands instead of the report
+    format: Option<Template>,
+    /// replace host names, paths and the like in the output with placeholders
+    anonymize: bool,
+    /// compare the statistics with those of a made-up average user instead of printing the report
+    leaderboard: bool,
+    /// only list the history files that would be read
+    dry_run: bool,
+    /// print a single line of `key=value` pairs instead of the report, see [`summary`]
+    summary_only: bool,
+    /// print the most frequent arguments of this command instead of the report
+    top_args: Option<String>,
+    /// another history to compare the commands with
+    compare: Option<PathBuf>,
+    /// file to write the deduplicated commands to, as a zsh history
+    export_history: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut result = Self::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-color" => result.color = Some(ColorChoice::Never),
+                "--color" => {
+                    let choice = args.next().as_deref().and_then(ColorChoice::parse);
+                    result.color =
+                        Some(choice.ok_or("--color expects one of auto, always, never")?);
+                }
+                "--top" => result.top = Some(parse_limit(&arg, args.next())?),
+                "--output-json" => result.output_json = true,
+                "--markdown" => result.markdown = true,
+                "--html" => {
+                    let path = args.next().ok_or("--html expects an output file")?;
+                    result.html = Some(PathBuf::from(path));
+                }
+                "--persist" => {
+                    let path = match args.next_if(|x| !x.starts_with("--")) {
+                        Some(path) => PathBuf::from(path),
+                        None => Store::default_path()
+                            .ok_or("could not determine the home directory for --persist")?,
+                    };
+                    result.persist = Some(path);
+                }
+                "--incremental" => result.incremental = true,
+                "--history-file" => {
+                    let path = args.next().ok_or("--history-file expects a path")?;
+                    result.history_files.push(PathBuf::from(path));
+                }
+                "--save-state" => {
+                    let path = args.next().ok_or("--save-state expects a path")?;
+                    result.save_state = Some(PathBuf::from(path));
+                }
+                "--since" | "--until" => {
+                    let date = args.next().as_deref().and_then(time::Date::parse);
+                    let date =
+                        date.ok_or_else(|| format!("{arg} expects a date like 2024-01-31"))?;
+                    let timestamp = date
+                        .start_of_day()
+                        .ok_or_else(|| format!("{arg}: {date} is out of range"))?;
+                    if arg == "--since" {
+                        result.range.since = Some(timestamp);
+                    } else {
+                        result.range.until = Some(timestamp);
+                    }
+                }
+                "--mmap" => result.mmap = true,
+                "--tui" => result.tui = true,
+                "--metrics-format" => match args.next().as_deref() {
+                    Some("prometheus") => result.prometheus = true,
+                    _ => return Err("--metrics-format expects prometheus".into()),
+                },
+                "--session-gap" => {
+                    result.session_gap = Some(parse_limit(&arg, args.next())? as u64)
+                }
+                "--export-history" => {
+                    let path = args.next().ok_or("--export-history expects a path")?;
+                    result.export_history = Some(PathBuf::from(path));
+                }
+                "--recency-decay" => {
+                    let lambda = args.next().and_then(|x| x.parse().ok());
+                    let lambda = lambda.filter(|x: &f64| x.is_finite() && *x >= 0.0);
+                    result.recency_decay =
+                        Some(lambda.ok_or("--recency-decay expects a non-negative number")?);
+                }
+                "--privacy-filter" => {
+                    let pattern = args
+                        .next()
+                        .ok_or("--privacy-filter expects a glob pattern")?;
+                    result.privacy_filter.push(pattern);
+                }
+                "--exclude-secrets" => result.exclude_secrets = true,
+                "--since-install" => result.since_install = true,
+                "--dry-run" => result.dry_run = true,
+                "--watch" => result.watch = Some(parse_limit(&arg, args.next())? as u64),
+                "--recent-days" => {
+                    result.recent_days = Some(parse_limit(&arg, args.next())? as u64)
+                }
+                "--leaderboard" => result.leaderboard = true,
+                "--summary-only" => result.summary_only = true,
+                "--anonymize" => result.anonymize = true,
+                "--search" | "--search-exact" => {
+                    let pattern = args
+                        .next()
+                        .ok_or_else(|| format!("{arg} expects a pattern"))?;
+                    result.search = Some(pattern);
+                    result.search_exact = arg == "--search-exact";
+                }
+                "--top-args" => {
+                    let cmd = args.next().ok_or("--top-args expects a command")?;
+                    result.top_args = Some(cmd);
+                }
+                "--format" => {
+                    let text = args.next().ok_or("--format expects a template")?;
+                    result.format =
+                        Some(Template::parse(&text).map_err(|e| format!("--format: {e}"))?);
+                }
+                "--compare" => {
+                    let path = args.next().ok_or("--compare expects a history file")?;
+                    result.compare = Some(PathBuf::from(path));
+                }
+                "--jobs" => result.jobs = Some(parse_limit(&arg, args.next())?),
+                "--load-state" => {
+                    let path = args.next().ok_or("--load-state expects a path")?;
+                    result.load_state.push(PathBuf::from(path));
+                }
+                "--output-csv" => {
+                    let dir = args.next_if(|x| !x.starts_with("--"));
+                    result.output_csv = Some(dir.map_or_else(|| PathBuf::from("."), PathBuf::from));
+                }
+                _ if arg.starts_with("--top-") => {
+                    let section = &arg["--top-".len()..];
+                    if DisplayConfig::uniform(0).section(section).is_none() {
+                        return Err(format!("unknown argument `{arg}`"));
                     }
-                    result.extend(&self.lines.next().unwrap().unwrap());
+                    let n = parse_limit(&arg, args.next())?;
+                    result.top_sections.push((section.to_owned(), n));
                 }
+                _ => return Err(format!("unknown argument `{arg}`")),
+            }
+        }
+        if result.incremental && result.persist.is_some() {
+            return Err("--incremental cannot be combined with --persist".into());
+        }
+        if result.watch.is_some() && result.tui {
+            return Err("--watch cannot be combined with --tui".into());
+        }
+        // every refresh would only count what was added since the previous one
+        if result.watch.is_some() && (result.incremental || result.persist.is_some()) {
+            let flag = if result.incremental {
+                "--incremental"
+            } else {
+                "--persist"
+            };
+            return Err(format!("--watch cannot be combined with {flag}"));
+        }
+        // only one output is written, and only the reports are styled by --markdown
+        let outputs = [
+            ("--compare", result.compare.is_some()),
+            ("--output-json", result.output_json),
+            ("--output-csv", result.output_csv.is_some()),
+            ("--html", result.html.is_some()),
+            ("--tui", result.tui),
+            ("--top-args", result.top_args.is_some()),
+            ("--format", result.format.is_some()),
+            ("--leaderboard", result.leaderboard),
+            ("--metrics-format", result.prometheus),
+            ("--summary-only", result.summary_only),
+        ];
+        let mut chosen = outputs.iter().filter(|x| x.1).map(|x| x.0);
+        if let (Some(first), Some(second)) = (chosen.next(), chosen.next()) {
+            return Err(format!("{first} cannot be combined with {second}"));
+        }
+        let styled = ["--compare", "--top-args", "--leaderboard"];
+        if let Some((flag, _)) = outputs
+            .iter()
+            .find(|x| result.markdown && x.1 && !styled.contains(&x.0))
+        {
+            return Err(format!("--markdown cannot be combined with {flag}"));
+        }
+        // the statistics are written as they are for other programs to read
+        let raw = [
+            ("--output-json", result.output_json),
+            ("--output-csv", result.output_csv.is_some()),
+            ("--metrics-format", result.prometheus),
+            ("--tui", result.tui),
+            ("--save-state", result.save_state.is_some()),
+            ("--export-history", result.export_history.is_some()),
+            ("--summary-only", result.summary_only),
+        ];
+        if let Some((flag, _)) = raw.iter().find(|x| result.anonymize && x.1) {
+            return Err(format!("--anonymize cannot be combined with {flag}"));
+        }
+        Ok(result)
+    }
 
-                return Some(result);
+    fn report_format(&self, config: &Config) -> Format {
+        let color = self.color.or(config.color).unwrap_or_default();
+        let style = if self.markdown {
+            Style::Markdown
+        } else {
+            Style::Text(Colorize::new(color))
+        };
+        Format {
+            style,
+            anonymize: self.anonymize,
+        }
+    }
+
+    /// The limits from `--top-commands` and the like, falling back to `--top` for all sections.
+    fn display_config(&self, config: &Config) -> DisplayConfig {
+        let mut display = match self.top.or(config.top_n) {
+            Some(n) => DisplayConfig::uniform(n),
+            None => DisplayConfig::DEFAULT,
+        };
+        for (section, n) in &self.top_sections {
+            if let Some(limit) = display.section(section) {
+                *limit = *n;
             }
         }
+        display
     }
 }
 
-#[derive(Default)]
-struct State {
-    man_pages: HashMap<String, u32>,
-    git_subcommands: HashMap<String, u32>,
-    commands: HashMap<String, u32>,
-}
-
-fn process_command_history(state: &mut State, command_history: &mut dyn CommandHistory) {
-    for entry in command_history {
-        let mut it = entry.split(|x| *x == b' ');
-        let mut cmd = it.next();
-        while let Some(icmd) = cmd {
-            // incomplete parsing of env-vars (in theory this could be an escaped equals sign, or part of a string)
-            if icmd.contains(&b'=') {
-                cmd = it.next();
+fn parse_limit(flag: &str, value: Option<String>) -> Result<usize, String> {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(n)) if n >= 1 => Ok(n),
+        _ => Err(format!("{flag} expects a number of at least 1")),
+    }
+}
+
+fn write_csv(dir: &Path, state: &State) -> std::io::Result<()> {
+    fn escape(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_owned()
+        }
+    }
+
+    for (name, map) in [
+        ("commands.csv", &state.commands),
+        ("git_subcommands.csv", &state.git_subcommands),
+    ] {
+        let mut csv = String::from("command,count\n");
+        for (count, key) in most_common(map) {
+            csv.push_str(&format!("{},{count}\n", escape(key)));
+        }
+        std::fs::write(dir.join(name), csv)?;
+    }
+    let mut csv = String::from("page,section,count\n");
+    for (count, (section, page)) in most_common(&state.man_pages) {
+        let section = if *section == 0 {
+            String::new()
+        } else {
+            section.to_string()
+        };
+        csv.push_str(&format!("{},{section},{count}\n", escape(page)));
+    }
+    std::fs::write(dir.join("man_pages.csv"), csv)
+}
+
+fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// How many columns `text` takes up on the screen, leaving out the escape sequences of colors.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|x| *x == 'm');
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// An hour of the day like `3pm`.
+fn twelve_hour(hour: u8) -> String {
+    let suffix = if hour < 12 { "am" } else { "pm" };
+    format!("{}{suffix}", (hour + 11) % 12 + 1)
+}
+
+fn bar(count: u64, max: u64) -> String {
+    "#".repeat((count * 40).div_ceil(max) as usize)
+}
+
+fn offset_cache_path() -> Option<PathBuf> {
+    let mut path = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home::home_dir()?.join(".cache"),
+    };
+    path.extend(["linux_wrapped", "offset"]);
+    Some(path)
+}
+
+/// Parses the zsh history from where the previous incremental run stopped and remembers where this
+/// one did.
+fn process_zsh_incrementally(state: &mut State, config: &Config, range: TimeRange) {
+    let Some(path) = ZshHistory::path() else {
+        return;
+    };
+    let cache = offset_cache_path();
+    let offset = cache
+        .as_ref()
+        .and_then(|x| std::fs::read_to_string(x).ok())
+        .and_then(|x| x.trim().parse().ok());
+    let Some(mut history) = ZshHistory::open(&path, offset) else {
+        return;
+    };
+    process_command_history(state, &mut within_range(&mut history, range, &path), config);
+    if let Some(cache) = cache {
+        let written = cache
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&cache, history.offset().to_string()));
+        if let Err(e) = written {
+            eprintln!("linux_wrapped: could not write {}: {e}", cache.display());
+        }
+    }
+}
+
+/// A piece of the histories that can be parsed on its own thread.
+enum Task {
+    /// a whole history
+    History(PathBuf, History),
+    /// a history for `--persist`, and how far earlier runs counted it
+    Persisted(PathBuf, History, Option<Cursor>),
+    /// the entries of a zsh history that start in a byte range, see [`ZshHistory::chunks`]
+    Chunk(PathBuf, u64, u64),
+}
+
+/// zsh histories are only split up between threads if every part gets at least this many bytes,
+/// below that starting the threads costs more than it saves
+const MIN_CHUNK_SIZE: u64 = 4 << 20;
+
+/// Byte ranges to parse a large zsh history in, `None` if it is not worth splitting up. Sessions
+/// and command pairs that span two ranges are counted as if the history ended in between.
+fn zsh_chunks(path: &Path, jobs: usize) -> Option<Vec<(u64, u64)>> {
+    let len = std::fs::metadata(path).ok()?.len();
+    let n = jobs.min((len / MIN_CHUNK_SIZE) as usize);
+    if n < 2 || HistoryFormat::detect(path).ok()? != HistoryFormat::Zsh {
+        return None;
+    }
+    ZshHistory::chunks(path, n).ok()
+}
+
+/// The histories to parse: the ones found automatically unless a saved state is loaded instead,
+/// and those given with `--history-file`.
+fn open_sources(args: &Args) -> Vec<(PathBuf, History)> {
+    // loading a saved state is meant to avoid parsing the histories again
+    let mut sources = if args.load_state.is_empty() {
+        history_sources(args.mmap)
+    } else {
+        Vec::new()
+    };
+    for path in &args.history_files {
+        // do not count a history twice if it was also found automatically
+        let canonical = path.canonicalize().ok();
+        if sources.iter().any(|x| {
+            x.0.canonicalize()
+                .ok()
+                .is_some_and(|x| Some(x) == canonical)
+        }) {
+            continue;
+        }
+        match open_history_file(path, args.mmap) {
+            Ok(history) => sources.push((path.clone(), history)),
+            Err(e) => {
+                eprintln!("linux_wrapped: could not read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    sources
+}
+
+/// An empty state with the analyzers that count what the state itself does not.
+fn with_analyzers() -> State {
+    State {
+        analyzers: vec![Box::new(PodmanAnalyzer::default())],
+        ..State::default()
+    }
+}
+
+/// Lists the history files that would be read, with their sizes, roughly how many entries they
+/// have and whether those have timestamps, without parsing them.
+fn dry_run(args: &Args) {
+    let mut paths: Vec<_> = if args.load_state.is_empty() {
+        HistoryFormat::ALL
+            .into_iter()
+            .filter_map(HistoryFormat::default_path)
+            .filter(|x| x.exists())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    paths.extend(args.history_files.iter().cloned());
+    if paths.is_empty() {
+        println!("No history files found.");
+    }
+    for path in paths {
+        let format = match HistoryFormat::detect(&path) {
+            Ok(format) => format,
+            Err(e) => {
+                println!("{}: could not read: {e}", path.display());
+                continue;
+            }
+        };
+        let size = match std::fs::read_dir(&path) {
+            Ok(dir) => dir
+                .flatten()
+                .filter_map(|x| x.metadata().ok())
+                .map(|x| x.len())
+                .sum(),
+            Err(_) => std::fs::metadata(&path).map_or(0, |x| x.len()),
+        };
+        let size = if size >= 1 << 20 {
+            format!("{:.1} MiB", size as f64 / f64::from(1 << 20))
+        } else {
+            format!("{:.1} KiB", size as f64 / 1024.0)
+        };
+        let entries = match approximate_entries(&path, format) {
+            Some((entries, true)) => format!("~{entries} entries with timestamps"),
+            Some((entries, false)) => format!("~{entries} entries without timestamps"),
+            None => "entry count unknown".into(),
+        };
+        println!("{} ({format:?}, {size}): {entries}", path.display());
+    }
+}
+
+/// The number of entries in a history, counted by its lines, and whether they have timestamps.
+/// `None` for the formats that are not line-based, whose entries cannot be counted by line.
+fn approximate_entries(path: &Path, format: HistoryFormat) -> Option<(u64, bool)> {
+    use std::io::BufRead;
+
+    if matches!(format, HistoryFormat::Nushell | HistoryFormat::Xonsh) {
+        return None;
+    }
+    let reader = std::io::BufReader::new(linux_wrapped::compress::open(path).ok()?);
+    let (mut lines, mut timestamps, mut fish_entries) = (0, 0, 0);
+    for line in reader.split(b'\n').map_while(Result::ok) {
+        lines += 1;
+        let is_timestamp = match format {
+            HistoryFormat::Zsh => line.starts_with(b": "),
+            HistoryFormat::Bash => {
+                line.len() > 1 && line[0] == b'#' && line[1..].iter().all(u8::is_ascii_digit)
+            }
+            HistoryFormat::Tcsh => line.starts_with(b"#+"),
+            _ => line.starts_with(b"  when:"),
+        };
+        timestamps += u64::from(is_timestamp);
+        fish_entries += u64::from(line.starts_with(b"- cmd:"));
+    }
+    let entries = match format {
+        // each entry starts with its timestamp
+        HistoryFormat::Zsh if timestamps > 0 => timestamps,
+        HistoryFormat::Zsh => lines,
+        HistoryFormat::Fish => fish_entries,
+        // the timestamps are on lines of their own
+        _ => lines - timestamps,
+    };
+    Some((entries, timestamps > 0))
+}
+
+/// Prints the command lines matching `pattern` with how often each was run, most frequent first.
+/// With `--anonymize`, the lines are counted after they are anonymized.
+fn search_history(args: &Args, config: &Config, pattern: &str) {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for (path, mut history) in open_sources(args) {
+        for entry in within_range(&mut history, args.range, &path) {
+            if config.privacy_filter.matches(&entry.command) {
+                continue;
+            }
+            let command = String::from_utf8_lossy(entry.command.trim_ascii());
+            let matches = if args.search_exact {
+                command.contains(pattern)
             } else {
-                break;
+                fuzzy_match(pattern, &command)
+            };
+            if matches && !command.is_empty() {
+                let command = match args.anonymize {
+                    true => anonymize::anonymize(&command),
+                    false => command.into_owned(),
+                };
+                *counts.entry(command).or_default() += 1;
             }
         }
-        let arg1 = it.next();
-        let arg2 = it.next();
-        (|| {
-            if let (Some(b"man"), Some(arg1), arg2) = (cmd, arg1, arg2) {
-                let mut page = arg1;
-                if arg1.iter().all(|x| x.is_ascii_digit()) {
-                    if let Some(arg2) = arg2 {
-                        page = arg2;
-                    } else {
-                        return Ok(());
+    }
+    let color = Colorize::new(args.color.or(config.color).unwrap_or_default());
+    for (count, command) in most_common(&counts) {
+        println!("{} {command}", color.count(format!("{count:>6}")));
+    }
+}
+
+fn main() {
+    let mut args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("linux_wrapped: {e}");
+        std::process::exit(2);
+    });
+    let installed = args.since_install.then(install_time).flatten();
+    if args.range.since.is_none() {
+        args.range.since = installed;
+    }
+    if let Some(days) = args.recent_days {
+        // combined with --since, the later start wins
+        let since = time::now().saturating_sub(days * 24 * 60 * 60);
+        args.range.since = args.range.since.max(Some(since));
+    }
+
+    let mut config = Config::load();
+    config.shell_aliases = AliasMap::load();
+    config.session_gap = args.session_gap.map(|x| x * 60);
+    config.recency_decay = args.recency_decay;
+    for pattern in &args.privacy_filter {
+        config.privacy_filter.add(pattern);
+    }
+    if args.exclude_secrets {
+        config.privacy_filter.exclude_secrets();
+    }
+
+    if args.dry_run {
+        dry_run(&args);
+        return;
+    }
+    if let Some(pattern) = &args.search {
+        search_history(&args, &config, pattern);
+        return;
+    }
+
+    if let Some(secs) = args.watch {
+        // nothing has to be restored on the way out, so Ctrl-C can just end the process
+        loop {
+            print!("\x1b[2J\x1b[H");
+            analyze(&args, &config, installed);
+            let _ = std::io::stdout().flush();
+            std::thread::sleep(std::time::Duration::from_secs(secs));
+        }
+    }
+    analyze(&args, &config, installed);
+}
+
+/// Reads the histories and writes the statistics as `args` asks for.
+fn analyze(args: &Args, config: &Config, installed: Option<u64>) {
+    let mut store = args.persist.as_ref().map(|path| {
+        Store::load(path).unwrap_or_else(|e| {
+            eprintln!("linux_wrapped: could not read {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    let mut state = with_analyzers();
+    if args.incremental {
+        process_zsh_incrementally(&mut state, config, args.range);
+    } else {
+        let sources = open_sources(args);
+        let jobs = args
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |x| x.get()));
+        let mut tasks = Vec::new();
+        for (path, history) in sources {
+            let Some(store) = &store else {
+                match zsh_chunks(&path, jobs) {
+                    Some(chunks) => tasks.extend(
+                        chunks
+                            .into_iter()
+                            .map(|(start, end)| Task::Chunk(path.clone(), start, end)),
+                    ),
+                    None => tasks.push(Task::History(path, history)),
+                }
+                continue;
+            };
+            let cursor = store.sources.get(&*path.to_string_lossy()).cloned();
+            tasks.push(Task::Persisted(path, history, cursor));
+        }
+        let results = parallel::map(jobs, tasks, |task| {
+            let mut source_state = with_analyzers();
+            let cursor = match task {
+                Task::Persisted(path, history, cursor) => {
+                    // only count what was appended to the history since the last run
+                    let (entries, cursor) = Cursor::unseen(cursor.as_ref(), history.collect());
+                    let mut entries = entries.into_iter();
+                    process_command_history(
+                        &mut source_state,
+                        &mut within_range(&mut entries, args.range, &path),
+                        config,
+                    );
+                    cursor.map(|x| (path, x))
+                }
+                Task::History(path, mut history) => {
+                    let mut history = within_range(&mut history, args.range, &path);
+                    process_command_history(&mut source_state, &mut history, config);
+                    None
+                }
+                Task::Chunk(path, start, end) => {
+                    match ZshHistory::open_range(&path, start, end, args.mmap) {
+                        Some(mut history) => {
+                            let mut history = within_range(&mut history, args.range, &path);
+                            process_command_history(&mut source_state, &mut history, config);
+                        }
+                        None => eprintln!("linux_wrapped: could not read {}", path.display()),
                     }
+                    None
                 }
-                *state
-                    .man_pages
-                    .entry(String::from_utf8(page.to_owned())?)
-                    .or_default() += 1;
-            }
-            Result::<(), Box<dyn std::error::Error>>::Ok(())
-        })()
-        .ok();
-        (|| {
-            match (cmd, arg1) {
-                (Some(b"g" | b"git"), Some(subcommand)) => {
-                    *state
-                        .git_subcommands
-                        .entry(String::from_utf8(subcommand.to_owned())?)
-                        .or_default() += 1;
-                }
-                (Some(b"gc" | b"gca"), _) => {
-                    *state
-                        .git_subcommands
-                        .entry(String::from_utf8(b"commit".to_vec())?)
-                        .or_default() += 1;
-                }
-                (Some(b"ga" | b"gau"), _) => {
-                    *state
-                        .git_subcommands
-                        .entry(String::from_utf8(b"add".to_vec())?)
-                        .or_default() += 1;
-                }
-                _ => {}
-            }
-            Result::<(), Box<dyn std::error::Error>>::Ok(())
-        })()
-        .ok();
-        (|| {
-            if let Some(cmd) = cmd {
-                *state
-                    .commands
-                    .entry(String::from_utf8(cmd.to_owned())?)
-                    .or_default() += 1;
             };
+            (source_state, cursor)
+        });
+        for (source_state, cursor) in results {
+            state = merge_states(state, source_state);
+            if let (Some(store), Some((path, cursor))) = (&mut store, cursor) {
+                store
+                    .sources
+                    .insert(path.to_string_lossy().into_owned(), cursor);
+            }
+        }
+    }
+    for path in &args.load_state {
+        match State::load(path) {
+            Ok(loaded) => state = merge_states(state, loaded),
+            Err(e) => {
+                eprintln!("linux_wrapped: could not read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+    }
+    if let (Some(path), Some(mut store)) = (&args.persist, store) {
+        store.state.merge(state);
+        // what is left out of the range now would never be counted by later runs
+        if !args.range.is_unbounded() {
+            eprintln!(
+                "linux_wrapped: warning: not updating {} because of --since/--until",
+                path.display()
+            );
+        } else if let Err(e) = store.save(path) {
+            eprintln!("linux_wrapped: could not write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        state = store.state;
+    }
+    if let Some(path) = &args.export_history {
+        // the statistics do not keep the command lines, so the histories are read again
+        let mut commands = Vec::new();
+        for (source, mut history) in open_sources(args) {
+            commands.extend(within_range(&mut history, args.range, &source));
+        }
+        let entries = export::deduplicate(commands, config);
+        if let Err(e) = export::write_zsh_history(&entries, path) {
+            eprintln!("linux_wrapped: could not write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &args.save_state {
+        if let Err(e) = state.save(path) {
+            eprintln!("linux_wrapped: could not write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.compare {
+        let mut other = State::default();
+        match open_history_file(path, args.mmap) {
+            Ok(mut history) => process_command_history(
+                &mut other,
+                &mut within_range(&mut history, args.range, path),
+                config,
+            ),
+            Err(e) => {
+                eprintln!("linux_wrapped: could not read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        }
+        let format = args.report_format(config);
+        print_comparison(&state, &other, path, format, args.display_config(config));
+    } else if args.output_json {
+        println!("{}", state.to_json());
+    } else if let Some(dir) = &args.output_csv {
+        if let Err(e) = write_csv(dir, &state) {
+            eprintln!(
+                "linux_wrapped: could not write CSV files to {}: {e}",
+                dir.display()
+            );
+            std::process::exit(1);
+        }
+    } else if let Some(path) = &args.html {
+        if let Err(e) = std::fs::write(
+            path,
+            html::render(&state, args.display_config(config), args.anonymize),
+        ) {
+            eprintln!("linux_wrapped: could not write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    } else if args.tui {
+        if let Err(e) = tui::run(&state) {
+            eprintln!("linux_wrapped: {e}");
+            std::process::exit(1);
+        }
+    } else if let Some(cmd) = &args.top_args {
+        let format = args.report_format(config);
+        print_top_args(&state, cmd, format, args.display_config(config));
+    } else if let Some(template) = &args.format {
+        let ranking = most_common(&state.commands);
+        for (i, (count, command)) in ranking
+            .into_iter()
+            .take(args.display_config(config).commands)
+            .enumerate()
+        {
+            let command = match args.anonymize {
+                true => anonymize::anonymize(command),
+                false => command.clone(),
+            };
+            let row = Row {
+                command: &command,
+                count,
+                rank: i + 1,
+                total: state.total_commands,
+            };
+            println!("{}", template.render(&row));
+        }
+    } else if args.leaderboard {
+        print_leaderboard(&state, config, args.report_format(config));
+    } else if args.prometheus {
+        print!("{}", prometheus::render(&state, config));
+    } else if args.summary_only {
+        println!("{}", summary(&state));
+    } else {
+        let format = args.report_format(config);
+        // without an install date, the history is taken to start with the system
+        let installed = args
+            .since_install
+            .then(|| installed.or(state.time_span.map(|x| x.0)))
+            .flatten();
+        print_report(
+            &state,
+            config,
+            format,
+            args.display_config(config),
+            args.range,
+            installed,
+            args.recent_days,
+        );
+    }
+}
+
+/// The statistics on one line for `--summary-only`, as space-separated `key=value` pairs. Scripts
+/// can rely on the keys, which are in this order:
+///
+/// - `total`: the number of commands
+/// - `top_command`: the most frequent command
+/// - `top_git`: the most frequent git subcommand
+/// - `days`: the number of calendar days from the first to the last timestamped command
+/// - `entropy`: the Shannon entropy of the commands in bits, with two decimals
+///
+/// Keys without a value, like `days` for a history without timestamps, are left out.
+fn summary(state: &State) -> String {
+    let top = |counts: &HashMap<String, u32>| most_common(counts).first().map(|x| x.1.clone());
+    let days = state.days();
+    let entropy = stats::shannon_entropy(&state.commands);
+    let pairs = [
+        ("total", Some(state.total_commands.to_string())),
+        ("top_command", top(&state.commands)),
+        ("top_git", top(&state.git_subcommands)),
+        ("days", days.map(|x| x.to_string())),
+        ("entropy", Some(format!("{entropy:.2}"))),
+    ];
+    pairs
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}={}", value?)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-            Result::<(), Box<dyn std::error::Error>>::Ok(())
-        })()
-        .ok();
+/// The statistics next to the made-up ones of [`benchmarks`], with where they would rank.
+fn print_leaderboard(state: &State, config: &Config, f: Format) {
+    f.heading("How you compare to everyone else:");
+    let mut rows = Vec::new();
+    if let Some(per_day) = average_commands_per_day(state).filter(|x| *x > 0.0) {
+        let days = state.total_commands as f64 / per_day;
+        let commits = state.git_subcommands.get("commit").copied().unwrap_or(0);
+        let per_week = f64::from(commits) / (days / 7.0).max(1.0);
+        let sessions = compute_sessions(&state.entry_timestamps, config.session_gap()).len();
+        let sessions = sessions as f64 / days;
+        rows.extend([
+            (
+                &benchmarks::COMMANDS_PER_DAY,
+                format!("{per_day:.0}"),
+                per_day,
+            ),
+            (
+                &benchmarks::COMMITS_PER_WEEK,
+                format!("{per_week:.1}"),
+                per_week,
+            ),
+            (
+                &benchmarks::SESSIONS_PER_DAY,
+                format!("{sessions:.1}"),
+                sessions,
+            ),
+        ]);
     }
+    let distinct = state.commands.len() as f64;
+    rows.push((
+        &benchmarks::DISTINCT_COMMANDS,
+        distinct.to_string(),
+        distinct,
+    ));
+    for (benchmark, shown, value) in rows {
+        f.line(format!(
+            "You {} ({} of users, typically {}).",
+            benchmark.describe(f.count(shown)),
+            benchmarks::rank_label(benchmark.percentile(value)),
+            benchmark.quantiles[2]
+        ));
+    }
+    if let Some((_, top)) = most_common(&state.commands).first() {
+        if benchmarks::TYPICAL_TOP_COMMANDS.contains(&top.as_str()) {
+            f.line(format!(
+                "Your top command is {}, like most people's.",
+                f.name(top)
+            ));
+        } else {
+            f.line(format!(
+                "Your top command is {}, where most people's is one of {}.",
+                f.name(top),
+                benchmarks::TYPICAL_TOP_COMMANDS.join(", ")
+            ));
+        }
+    }
+    println!();
+    f.line(benchmarks::DISCLAIMER);
 }
 
-fn main() {
+/// The arguments `cmd` was run with most often, with the share of its runs they appeared in.
+fn print_top_args(state: &State, cmd: &str, f: Format, display: DisplayConfig) {
+    let runs = state.commands.get(cmd).copied().unwrap_or(0);
+    let Some(args) = state.per_command_args.get(cmd).filter(|_| runs > 0) else {
+        f.line(format!("You never ran {} with arguments.", f.name(cmd)));
+        return;
+    };
+    f.heading(format!(
+        "Your most frequent arguments to {}, which you ran {} times:",
+        f.plain(cmd),
+        f.count(runs)
+    ));
+    f.list(
+        "Argument",
+        most_common(args)
+            .into_iter()
+            .take(display.arguments)
+            .map(|(count, arg)| {
+                let share = f64::from(count) / f64::from(runs) * 100.0;
+                (count, format!("{arg} ({share:.0}%)"))
+            }),
+    );
+}
+
+/// The commands that differ between `state` and the history at `path`, parsed into `other`.
+fn print_comparison(state: &State, other: &State, path: &Path, f: Format, display: DisplayConfig) {
+    let diff = diff_states(state, other);
+    let name = f.plain(path.display());
+    f.heading(format!(
+        "Comparing your {} commands with the {} in {name}:",
+        f.count(state.total_commands),
+        f.count(other.total_commands)
+    ));
+    if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+        f.line("Both histories ran exactly the same commands.");
+        return;
+    }
+    if !diff.changed.is_empty() {
+        println!();
+        f.heading("Commands run more or less often:");
+        f.list(
+            "Command",
+            diff.changed
+                .iter()
+                .take(display.comparison)
+                .map(|(cmd, before, after)| {
+                    let change = i64::from(*after) - i64::from(*before);
+                    (format!("{change:+}"), format!("{cmd} ({before} → {after})"))
+                }),
+        );
+    }
+    if !diff.removed.is_empty() {
+        println!();
+        f.heading("Only in your history:");
+        f.list(
+            "Command",
+            diff.removed
+                .iter()
+                .take(display.comparison)
+                .map(|(cmd, count)| (count, cmd)),
+        );
+    }
+    if !diff.added.is_empty() {
+        println!();
+        f.heading(format!("Only in {name}:"));
+        f.list(
+            "Command",
+            diff.added
+                .iter()
+                .take(display.comparison)
+                .map(|(cmd, count)| (count, cmd)),
+        );
+    }
+}
+
+/// `installed` is when the system was installed, for `--since-install`, and `recent_days` the
+/// number of days looked at for `--recent-days`, whose dates are shown at the top.
+fn print_report(
+    state: &State,
+    config: &Config,
+    f: Format,
+    display: DisplayConfig,
+    range: TimeRange,
+    installed: Option<u64>,
+    recent_days: Option<u64>,
+) {
     let mut rng = rand::thread_rng();
-    let mut state = State::default();
-    if let Some(mut h) = ZshHistory::new() {
-        process_command_history(&mut state, &mut h);
+
+    if let Style::Markdown = f.style {
+        println!("# Your linux_wrapped\n");
+    }
+    if recent_days.is_some() {
+        let date = |x: u64| {
+            time::local_time(x)
+                .map(|x| x.date.to_string())
+                .unwrap_or_default()
+        };
+        // `until` is exclusive
+        let until = range.until.map_or_else(time::now, |x| x.saturating_sub(1));
+        f.line(format!(
+            "Looking at your history from {} to {}:",
+            f.count(date(range.since.unwrap_or(0))),
+            f.count(date(until))
+        ));
+    }
+    if let Some(installed) = installed {
+        let days = time::now().saturating_sub(installed) / (24 * 60 * 60);
+        f.line(format!(
+            "Since you installed this system {} days ago...",
+            f.count(days)
+        ));
+    }
+
+    let sessions = compute_sessions(&state.entry_timestamps, config.session_gap());
+    if state.total_commands > 0 {
+        let mut total = format!(
+            "You ran {} commands {}",
+            f.count(state.total_commands),
+            range.describe()
+        );
+        if !sessions.is_empty() {
+            total.push_str(&format!(
+                " across roughly {} sessions",
+                f.count(sessions.len())
+            ));
+        }
+        total.push('!');
+        f.heading(total);
+        let messages: &[&str] = match state.total_commands {
+            ..1_000 => &[
+                "A light touch. Do you even need a terminal?",
+                "Quality over quantity, surely.",
+            ],
+            1_000..10_000 => &[
+                "A respectable amount of typing.",
+                "The terminal is clearly part of your life.",
+            ],
+            10_000..100_000 => &[
+                "Your keyboard has seen things.",
+                "That's a lot of Enter key presses.",
+            ],
+            _ => &[
+                "Do you ever leave the shell?",
+                "Your history file deserves its own backup strategy.",
+            ],
+        };
+        f.line(messages.choose(&mut rng).unwrap());
+        if let Some(average) = average_commands_per_day(state) {
+            f.line(format!(
+                "You average {} commands per day.",
+                f.count(format!("{average:.2}"))
+            ));
+        }
+        if let Some((date, count)) = busiest_day(&state.entry_timestamps) {
+            f.line(format!(
+                "On your busiest day, {date}, you ran {} commands.",
+                f.count(count)
+            ));
+        }
+        println!();
+    }
+
+    if !sessions.is_empty() {
+        let timed: usize = sessions.iter().map(|x| x.commands).sum();
+        f.line(format!(
+            "You had {} terminal sessions averaging {} commands each.",
+            f.count(sessions.len()),
+            f.count(format!("{:.1}", timed as f64 / sessions.len() as f64))
+        ));
+        let started = |session: &Session| {
+            time::local_time(session.start).map_or_else(String::new, |x| format!(" on {}", x.date))
+        };
+        let most = sessions.iter().max_by_key(|x| x.commands).unwrap();
+        f.line(format!(
+            "Your busiest session{} had {} commands.",
+            started(most),
+            f.count(most.commands)
+        ));
+        let longest = sessions.iter().max_by_key(|x| x.duration()).unwrap();
+        f.line(format!(
+            "Your longest session{} lasted {}.",
+            started(longest),
+            f.count(format_duration(longest.duration()))
+        ));
+        println!();
+    }
+
+    if let Some(days) = state.days() {
+        f.heading(format!(
+            "Your history covers {days} days of terminal usage."
+        ));
+        let days = daily_counts(&state.entry_timestamps);
+        if days.len() >= 7 {
+            f.preformatted(render_ascii_graph(&days, 60, 8));
+            let recent: Vec<u64> = days[days.len().saturating_sub(30)..]
+                .iter()
+                .map(|x| x.1)
+                .collect();
+            f.line(format!(
+                "Your last {} days: {}",
+                recent.len(),
+                sparkline(&recent)
+            ));
+        }
+        println!();
+    }
+
+    let hours: Vec<_> = state
+        .hourly_counts
+        .iter()
+        .enumerate()
+        .map(|(hour, count)| (format!("{hour:02}:00"), *count))
+        .collect();
+    if hours.iter().any(|x| x.1 > 0) {
+        f.heading("When you are at the terminal:");
+        f.chart("Hour", &hours);
+        let buckets = hourly_buckets(&state.entry_timestamps);
+        if let Some((hour, rate)) = stats::peak_hour(&buckets) {
+            f.line(format!(
+                "Your most intense coding hour was {} with {} commands per minute.",
+                f.name(twelve_hour(hour)),
+                f.count(format!("{rate:.2}"))
+            ));
+        }
+        if let Some((hour, rate)) = stats::quietest_hour(&buckets).filter(|_| buckets.len() > 1) {
+            f.line(format!(
+                "Your quietest active hour was {} with {} commands per minute.",
+                f.name(twelve_hour(hour)),
+                f.count(format!("{rate:.2}"))
+            ));
+        }
+        println!();
+    }
+
+    let busiest_weekday = (0..7).max_by_key(|x| state.weekday_counts[*x]).unwrap();
+    let quietest_weekday = (0..7).min_by_key(|x| state.weekday_counts[*x]).unwrap();
+    if state.weekday_counts[busiest_weekday] > 0 {
+        f.heading(format!(
+            "Your most productive day is {}.",
+            time::WEEKDAYS[busiest_weekday]
+        ));
+        let weekdays: Vec<_> = time::WEEKDAYS
+            .iter()
+            .zip(state.weekday_counts)
+            .map(|(weekday, count)| (weekday[..3].to_owned(), count))
+            .collect();
+        f.chart("Day", &weekdays);
+        f.line(format!(
+            "On {}s you mostly leave the terminal alone.",
+            time::WEEKDAYS[quietest_weekday]
+        ));
+        println!();
+    }
+
+    if state.weekly_counts.len() > 1 {
+        let week = |timestamp| time::local_time(timestamp).map(|x| x.date.iso_week());
+        let this_week = week(time::now());
+        let last_week = week(time::now().saturating_sub(7 * 24 * 60 * 60));
+        let count = |week: Option<(i32, u8)>| {
+            week.and_then(|x| state.weekly_counts.get(&x))
+                .copied()
+                .unwrap_or(0)
+        };
+        let (current, previous) = (count(this_week), count(last_week));
+        if current > 0 && previous > 0 {
+            let change = (current as f64 / previous as f64 - 1.0) * 100.0;
+            let direction = if change >= 0.0 { "more" } else { "less" };
+            f.line(format!(
+                "This week you ran {} commands — that's {:.0}% {direction} than last week.",
+                f.count(current),
+                change.abs()
+            ));
+        } else if current > 0 {
+            f.line(format!("This week you ran {} commands.", f.count(current)));
+        }
+        let label = |(year, week): (i32, u8)| format!("{year}-W{week:02}");
+        let busiest = state
+            .weekly_counts
+            .iter()
+            .max_by_key(|(week, count)| (**count, *week));
+        let quietest = state
+            .weekly_counts
+            .iter()
+            .min_by_key(|(week, count)| (**count, *week));
+        if let (Some((busiest, most)), Some((quietest, least))) = (busiest, quietest) {
+            f.line(format!(
+                "Your most productive week was {} with {} commands, your least productive {} with {}.",
+                label(*busiest),
+                f.count(most),
+                label(*quietest),
+                f.count(least)
+            ));
+        }
+        println!();
+    }
+
+    if !state.entry_timestamps.is_empty() {
+        let (current, longest) = compute_streaks(&state.entry_timestamps);
+        f.line(format!("Current streak: {} days", f.count(current)));
+        f.line(format!("Longest streak: {} days", f.count(longest)));
+        println!();
+    }
+
+    let mut learned: HashMap<(i32, u8), u64> = HashMap::new();
+    for timestamp in state.commands_first_seen.values() {
+        if let Some(local) = time::local_time(*timestamp) {
+            *learned
+                .entry((local.date.year, local.date.month))
+                .or_default() += 1;
+        }
+    }
+    if let (Some(first), Some(last)) = (learned.keys().min(), learned.keys().max()) {
+        let mut months = Vec::new();
+        let (mut year, mut month) = *first;
+        while (year, month) <= *last {
+            let count = learned.get(&(year, month)).copied().unwrap_or(0);
+            months.push((format!("{year:04}-{month:02}"), count));
+            (year, month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+        }
+        f.heading("New commands you learned each month:");
+        f.chart("Month", &months);
+        println!();
     }
 
-    let mut most_used_man_pages: Vec<_> = state.man_pages.iter().map(|x| (x.1, x.0)).collect();
     let lookups: u64 = state.man_pages.iter().map(|x| *x.1 as u64).sum();
     if lookups > 0 {
-        most_used_man_pages.sort_unstable();
-        println!("You looked up manual pages a total of {lookups} times! #RTFM");
-        println!(
-            "{}",
+        f.heading(format!(
+            "You looked up manual pages a total of {lookups} times! #RTFM"
+        ));
+        f.line(
             [
                 "You just couldn't get enough of reading these manuals:",
-                "In desperate times you turned to these man pages:"
+                "In desperate times you turned to these man pages:",
             ]
             .choose(&mut rng)
-            .unwrap()
+            .unwrap(),
         );
-        for (count, man_page) in most_used_man_pages.iter().rev().take(15) {
-            println!("{count} {man_page}");
+        let mut by_section: Vec<_> = most_common(&state.man_pages)
+            .into_iter()
+            .take(display.man)
+            .collect();
+        by_section.sort_by_key(|(_, (section, _))| *section);
+        for group in by_section.chunk_by(|a, b| a.1 .0 == b.1 .0) {
+            match group[0].1 .0 {
+                0 => f.line("(no section)"),
+                section => f.line(format!("(section {section})")),
+            }
+            f.list("Page", group.iter().map(|(count, (_, page))| (count, page)));
         }
         println!();
     }
 
-    let mut most_used_subcommands: Vec<_> =
-        state.git_subcommands.iter().map(|x| (x.1, x.0)).collect();
-    most_used_subcommands.sort_unstable();
-    println!("Your favorite git subcommands are:");
-    for (count, cmd) in most_used_subcommands.iter().rev().take(5) {
-        println!("{count} {cmd}");
-    }
+    f.heading("Your favorite git subcommands are:");
+    f.list(
+        "Subcommand",
+        most_common(&state.git_subcommands)
+            .into_iter()
+            .take(display.git),
+    );
     println!();
 
-    let mut most_used_commands: Vec<_> = state.commands.iter().map(|x| (x.1, x.0)).collect();
-    most_used_commands.sort_unstable();
-    println!("Your top commands are:");
-    for (count, cmd) in most_used_commands.iter().rev().take(15) {
-        println!("{count} {cmd}");
+    if !state.git_flags.is_empty() {
+        f.heading("Your most-used git flags:");
+        f.list(
+            "Flag",
+            most_common(&state.git_flags)
+                .into_iter()
+                .take(display.git_flags),
+        );
+        let forced = ["--force", "-f"]
+            .iter()
+            .filter_map(|x| state.git_flags.get(*x))
+            .sum::<u32>();
+        if forced > FORCE_WARNING {
+            f.line(format!(
+                "⚠️  You forced git {} times. Have you heard of --force-with-lease?",
+                f.count(forced)
+            ));
+        }
+        println!();
+    }
+
+    if !state.cargo_subcommands.is_empty() {
+        f.heading("Your most-used cargo subcommands:");
+        f.list(
+            "Subcommand",
+            most_common(&state.cargo_subcommands)
+                .into_iter()
+                .take(display.cargo),
+        );
+        println!();
+    }
+
+    if !state.likely_typos.is_empty() {
+        f.heading("You may have mistyped these commands:");
+        f.list(
+            "Typo",
+            state
+                .likely_typos
+                .iter()
+                .take(display.typos)
+                .map(|(typo, intended, count)| (count, format!("{typo} (meant {intended}?)"))),
+        );
+        println!();
+    }
+
+    f.heading("Your top commands are:");
+    f.list(
+        "Command",
+        most_common(&state.commands)
+            .into_iter()
+            .take(display.commands),
+    );
+    f.line("... maybe consider sponsoring them?");
+
+    let vocabularies: [(&str, Vec<u32>); 3] = [
+        ("commands", state.commands.values().copied().collect()),
+        (
+            "git subcommands",
+            state.git_subcommands.values().copied().collect(),
+        ),
+        ("man pages", state.man_pages.values().copied().collect()),
+    ];
+    for (what, counts) in vocabularies {
+        if counts.is_empty() {
+            continue;
+        }
+        let (p50, p90, p99) = compute_percentiles(&counts);
+        f.line(format!(
+            "50% of your {what} were used {} times or fewer, 90% {} or fewer; the top 1% were used {} times or more.",
+            f.count(p50),
+            f.count(p90),
+            f.count(p99)
+        ));
+    }
+
+    if !state.command_categories.is_empty() {
+        println!();
+        f.heading("How you spend your time:");
+        f.list("Category", most_common(&state.command_categories));
+    }
+
+    if !state.builtins.is_empty() {
+        println!();
+        f.heading("Your most-used shell builtins:");
+        f.list(
+            "Builtin",
+            most_common(&state.builtins)
+                .into_iter()
+                .take(display.builtins),
+        );
+        let builtins: u32 = state.builtins.values().sum();
+        let external = state.commands.values().sum::<u32>() - builtins;
+        f.line(format!(
+            "You ran {} builtins for every external command.",
+            f.count(format!(
+                "{:.2}",
+                f64::from(builtins) / f64::from(external.max(1))
+            ))
+        ));
+    }
+
+    if !state.command_timestamps.is_empty() {
+        println!();
+        f.heading("What you've been into lately:");
+        let ranking = weighted_ranking(
+            &state.command_timestamps,
+            time::now(),
+            config.recency_decay(),
+        );
+        f.list(
+            "Command",
+            ranking
+                .into_iter()
+                .take(display.recent)
+                .map(|(score, cmd)| (format!("{score:.1}"), cmd)),
+        );
+    }
+
+    let (weekday, weekend) = compute_weekday_weekend(&state.command_timestamps);
+    if !weekday.is_empty() && !weekend.is_empty() {
+        println!();
+        f.heading("Weekdays vs. weekends:");
+        let weekday = most_common(&weekday);
+        let weekend = most_common(&weekend);
+        f.columns(
+            ["Weekdays", "Weekends"],
+            (0..weekday.len().max(weekend.len()).min(display.weekdays)).map(|i| {
+                [&weekday, &weekend].map(|x| x.get(i).map(|(count, cmd)| (*count, **cmd)))
+            }),
+        );
+        if weekday[0].1 == weekend[0].1 {
+            f.line(format!(
+                "You focus on {} all week long, a uniform workflow.",
+                f.name(weekday[0].1)
+            ));
+        } else {
+            f.line(format!(
+                "On weekdays you focus on {}, on weekends you use {}.",
+                f.name(weekday[0].1),
+                f.name(weekend[0].1)
+            ));
+        }
+    }
+
+    if !state.commands.is_empty() {
+        let entropy = stats::shannon_entropy(&state.commands);
+        let inequality = stats::gini(&state.commands.values().copied().collect::<Vec<_>>());
+        let kind = match entropy {
+            x if x >= 5.0 => "a power user",
+            x if x >= 3.0 => "a specialist",
+            _ => "a one-trick pony",
+        };
+        println!();
+        f.line(format!(
+            "Your command entropy is {} bits, which makes you {kind}.",
+            f.count(format!("{entropy:.2}"))
+        ));
+        f.line(format!(
+            "The Gini coefficient of your command usage is {}.",
+            f.count(format!("{inequality:.2}"))
+        ));
+        let counts: Vec<u32> = most_common(&state.commands)
+            .into_iter()
+            .map(|x| x.0)
+            .collect();
+        if counts.len() >= 3 {
+            let fit = stats::zipf_fit(&counts);
+            let (verdict, meaning) = if fit >= 0.9 {
+                ("follows Zipf's law", "a stable workflow")
+            } else {
+                ("strays from Zipf's law", "varied usage patterns")
+            };
+            f.line(format!(
+                "Your command usage {verdict} with R²={}, a sign of {meaning}.",
+                f.count(format!("{fit:.2}"))
+            ));
+        }
+    }
+
+    if let Some((cmd, len)) = &state.longest_command {
+        let cmd = String::from_utf8_lossy(cmd);
+        let mut shown: String = cmd.chars().take(80).collect();
+        if shown.len() < cmd.len() {
+            shown.push_str("...");
+        }
+        println!();
+        f.line(format!(
+            "Your longest command was {} characters:",
+            f.count(len)
+        ));
+        f.line(f.name(shown));
+    }
+    if let Some((cmd, args)) = &state.most_args_command {
+        f.line(format!(
+            "Your most argument-heavy command was {} with {} args.",
+            f.name(cmd),
+            f.count(args)
+        ));
+    }
+
+    if !state.argument_words.is_empty() {
+        println!();
+        f.heading("Your most-typed argument words:");
+        f.list(
+            "Word",
+            most_common(&state.argument_words)
+                .into_iter()
+                .take(display.words),
+        );
+    }
+
+    if !state.commands_by_directory.is_empty() {
+        println!();
+        f.heading("Your most active directories and what you do there:");
+        let mut directories: Vec<_> = state
+            .commands_by_directory
+            .iter()
+            .map(|(dir, commands)| (commands.values().sum::<u32>(), dir, commands))
+            .collect();
+        directories.sort_unstable_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        for (total, dir, commands) in directories.into_iter().take(display.active_directories) {
+            f.line(format!("{} ({} commands):", f.name(dir), f.count(total)));
+            f.list(
+                "Command",
+                most_common(commands)
+                    .into_iter()
+                    .take(display.directory_commands),
+            );
+        }
+    }
+
+    if !state.command_bigrams.is_empty() {
+        println!();
+        f.heading("Your most common command sequences:");
+        f.list(
+            "Sequence",
+            most_common(&state.command_bigrams)
+                .into_iter()
+                .take(display.bigrams)
+                .map(|(count, (first, second))| (count, format!("{first} → {second}"))),
+        );
+    }
+
+    if !state.pipe_transitions.is_empty() {
+        println!();
+        f.heading("Your most common pipelines:");
+        f.list(
+            "Pipeline",
+            most_common(&state.pipe_transitions)
+                .into_iter()
+                .take(display.pipelines)
+                .map(|(count, (from, to))| (count, format!("{from} | {to}"))),
+        );
+    }
+
+    if state.background_jobs > 0 {
+        println!();
+        f.line(format!(
+            "You ran {} commands in the background.",
+            f.count(state.background_jobs)
+        ));
+        f.list(
+            "Command",
+            most_common(&state.backgrounded_commands)
+                .into_iter()
+                .take(display.background),
+        );
+    }
+
+    if state.undo_count > 0 || state.destructive_commands > 0 {
+        println!();
+    }
+    if state.undo_count > 0 {
+        f.line(format!(
+            "You undid things {} times — no judgment!",
+            f.count(state.undo_count)
+        ));
+    }
+    if state.destructive_commands > 0 {
+        f.line(format!(
+            "{:.1}% of your commands were destructive (rm -rf, truncate, dd). Careful out there.",
+            state.danger_ratio * 100.0
+        ));
+    }
+
+    let suggestions = suggest_aliases(state, config);
+    if !suggestions.is_empty() {
+        println!();
+        f.heading("Consider adding these aliases to your shell config:");
+        for (name, expansion, count) in suggestions.iter().take(display.aliases) {
+            let line = format!("alias {name}='{}'", expansion.replace('\'', r"'\''"));
+            f.line(format!(
+                "{} (you ran this {} times)",
+                f.name(line),
+                f.count(count)
+            ));
+        }
+    }
+
+    if state.sudo_count > 0 {
+        println!();
+        f.line(format!(
+            "You ran commands as root {} times.",
+            f.count(state.sudo_count)
+        ));
+    }
+
+    if !state.package_manager_ops.is_empty() {
+        let changes: u32 = state
+            .package_manager_ops
+            .iter()
+            .filter(|x| changes_packages(x.0))
+            .map(|x| x.1)
+            .sum();
+        println!();
+        f.heading("Your package manager operations:");
+        f.line(format!(
+            "You installed/updated packages {} times.",
+            f.count(changes)
+        ));
+        f.list(
+            "Operation",
+            most_common(&state.package_manager_ops)
+                .into_iter()
+                .take(display.packages),
+        );
+    }
+
+    if !state.docker_ops.is_empty() {
+        let mut images = HashMap::new();
+        let mut subcommands = HashMap::new();
+        for ((subcommand, image), count) in &state.docker_ops {
+            if let Some(image) = image {
+                *images.entry(image).or_insert(0) += count;
+            }
+            *subcommands.entry(subcommand).or_insert(0) += count;
+        }
+        if !images.is_empty() {
+            println!();
+            f.heading("Your most-used Docker images:");
+            f.list(
+                "Image",
+                most_common(&images).into_iter().take(display.docker_images),
+            );
+        }
+        println!();
+        f.heading("Your most-used Docker subcommands:");
+        f.list(
+            "Subcommand",
+            most_common(&subcommands).into_iter().take(display.docker),
+        );
+    }
+
+    if !state.k8s_ops.is_empty() {
+        println!();
+        f.heading("Your top kubectl operations:");
+        f.list(
+            "Operation",
+            most_common(&state.k8s_ops)
+                .into_iter()
+                .take(display.kubectl),
+        );
+    }
+
+    if !state.npm_ops.is_empty() {
+        println!();
+        f.heading("Your JavaScript workflow:");
+        f.list(
+            "Operation",
+            most_common(&state.npm_ops).into_iter().take(display.npm),
+        );
+        let mut managers: Vec<_> = state
+            .npm_ops
+            .keys()
+            .filter_map(|x| x.split(':').next())
+            .collect();
+        managers.sort_unstable();
+        managers.dedup();
+        if managers.len() > 1 {
+            f.line(format!(
+                "You mixed {} package managers: {}. Maybe pick one?",
+                managers.len(),
+                managers.join(", ")
+            ));
+        }
+    }
+
+    if !state.systemctl_ops.is_empty() {
+        println!();
+        f.heading("Services you manage most:");
+        f.list(
+            "Operation",
+            most_common(&state.systemctl_ops)
+                .into_iter()
+                .take(display.services),
+        );
+    }
+
+    if let Some(filter) = &state.longest_jq_filter {
+        println!();
+        f.heading("Your most complex jq filter:");
+        let shown: String = filter.chars().take(60).collect();
+        let ellipsis = if shown.len() < filter.len() {
+            "…"
+        } else {
+            ""
+        };
+        f.line(f.name(format!("{shown}{ellipsis}")));
+    }
+
+    if !state.cloud_ops.is_empty() {
+        println!();
+        f.heading("Your cloud operations:");
+        f.list(
+            "Operation",
+            most_common(&state.cloud_ops)
+                .into_iter()
+                .take(display.cloud),
+        );
+    }
+
+    if !state.terraform_ops.is_empty() {
+        println!();
+        f.heading("Your Terraform operations:");
+        f.list(
+            "Operation",
+            most_common(&state.terraform_ops)
+                .into_iter()
+                .take(display.terraform),
+        );
+        if let Some(applies) = state.terraform_ops.get("apply") {
+            f.line(format!(
+                "You've changed infrastructure {} times — brave!",
+                f.count(applies)
+            ));
+        }
+    }
+
+    if !state.tmux_subcommands.is_empty() {
+        println!();
+        f.heading("Your tmux subcommands:");
+        f.list(
+            "Subcommand",
+            most_common(&state.tmux_subcommands)
+                .into_iter()
+                .take(display.tmux),
+        );
+    }
+
+    if !state.sessions_by_name.is_empty() {
+        println!();
+        f.heading("Your busiest named sessions:");
+        f.list(
+            "Session",
+            most_common(&state.sessions_by_name)
+                .into_iter()
+                .take(display.sessions),
+        );
+    }
+
+    if !state.nix_ops.is_empty() {
+        println!();
+        f.heading("Your Nix operations:");
+        f.list(
+            "Operation",
+            most_common(&state.nix_ops).into_iter().take(display.nix),
+        );
+        let total: u32 = state.nix_ops.values().sum();
+        if state.nix_flake_commands == 0 {
+            f.line("You're on classic Nix, without flakes.");
+        } else {
+            f.line(format!(
+                "{} of your Nix commands used flakes.",
+                f.count(format!(
+                    "{:.0}%",
+                    state.nix_flake_commands as f64 / f64::from(total) * 100.0
+                ))
+            ));
+        }
+    }
+
+    if !state.ansible_ops.is_empty() {
+        println!();
+        f.heading("Your Ansible operations:");
+        f.list(
+            "Operation",
+            most_common(&state.ansible_ops)
+                .into_iter()
+                .take(display.ansible),
+        );
+    }
+
+    if !state.tar_ops.is_empty() {
+        println!();
+        f.heading("Your tar operations breakdown:");
+        f.list(
+            "Operation",
+            most_common(&state.tar_ops).into_iter().take(display.tar),
+        );
+        let lookups = state
+            .man_pages
+            .iter()
+            .filter(|x| x.0 .1 == "tar")
+            .map(|x| x.1)
+            .sum::<u32>()
+            + state.help_sought.get("tar").copied().unwrap_or(0);
+        if lookups > 0 {
+            f.line(format!(
+                "You looked up how tar works {} times.",
+                f.count(lookups)
+            ));
+        }
+    }
+
+    if !state.build_targets.is_empty() {
+        println!();
+        f.heading("Your most-built targets:");
+        f.list(
+            "Target",
+            most_common(&state.build_targets)
+                .into_iter()
+                .take(display.build_targets),
+        );
+    }
+
+    if !state.search_patterns.is_empty() {
+        println!();
+        f.heading("Your most-searched-for patterns:");
+        f.list(
+            "Pattern",
+            most_common(&state.search_patterns)
+                .into_iter()
+                .take(display.search_patterns),
+        );
+    }
+    let searches: u32 = state.search_tools.values().sum();
+    if searches > 0 {
+        let tools = most_common(&state.search_tools);
+        f.line(format!(
+            "You searched {} times, with {}.",
+            f.count(searches),
+            tools
+                .iter()
+                .map(|(count, tool)| format!("{} {count} times", f.name(tool)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        let ripgrep = ["rg", "ripgrep"]
+            .iter()
+            .filter_map(|x| state.search_tools.get(*x))
+            .sum::<u32>();
+        if ripgrep * 2 > searches {
+            f.line("You've embraced ripgrep!");
+        }
+    }
+
+    if !state.http_targets.is_empty() {
+        println!();
+        f.heading("APIs and sites you curl'd most:");
+        f.list(
+            "Host",
+            most_common(&state.http_targets)
+                .into_iter()
+                .take(display.http)
+                .map(|(count, host)| (count, f.host(host))),
+        );
+    }
+
+    if let Some((_, cmd)) = most_common(&state.help_sought).first() {
+        println!();
+        f.heading("Commands you most frequently need help with:");
+        f.list(
+            "Command",
+            most_common(&state.help_sought)
+                .into_iter()
+                .take(display.help),
+        );
+        f.line(format!("You always forget how {} works.", f.name(cmd)));
+    }
+
+    if !state.venv_activations.is_empty() {
+        println!();
+        f.heading("Python environments you used most:");
+        f.list(
+            "Environment",
+            most_common(&state.venv_activations)
+                .into_iter()
+                .take(display.venvs),
+        );
+    }
+
+    if !state.ssh_hosts.is_empty() {
+        println!();
+        f.heading("Your most SSHed-into machines:");
+        f.list(
+            "Host",
+            most_common(&state.ssh_hosts)
+                .into_iter()
+                .take(display.ssh)
+                .map(|(count, host)| (count, f.host(host))),
+        );
+    }
+
+    if !state.directories.is_empty() {
+        println!();
+        f.heading("Directories you visited most:");
+        f.list(
+            "Directory",
+            most_common(&state.directories)
+                .into_iter()
+                .take(display.directories),
+        );
+    }
+    if state.previous_dir_count > 0 {
+        f.line(format!(
+            "You jumped back with `cd -` {} times.",
+            f.count(state.previous_dir_count)
+        ));
+    }
+
+    if let Some((count, editor)) = most_common(&state.editors).first() {
+        println!();
+        f.line(format!(
+            "Your editor of choice is {}, started {} times.",
+            f.name(editor),
+            f.count(count)
+        ));
+    }
+    if !state.editor_files.is_empty() {
+        f.heading("Files you edited most:");
+        f.list(
+            "File",
+            most_common(&state.editor_files)
+                .into_iter()
+                .take(display.files),
+        );
+    }
+
+    let pipelines: u64 = state
+        .pipeline_depth_histogram
+        .iter()
+        .filter(|x| *x.0 > 1)
+        .map(|x| x.1)
+        .sum();
+    if pipelines > 0 {
+        let longest = state.pipeline_depth_histogram.keys().max().unwrap();
+        println!();
+        f.line(format!(
+            "You strung commands together with | {} times, at most {} at once.",
+            f.count(pipelines),
+            f.count(longest)
+        ));
+    }
+
+    if !config.interesting.is_empty() {
+        println!();
+        f.heading("The commands you keep an eye on:");
+        f.list(
+            "Command",
+            config
+                .interesting
+                .iter()
+                .map(|cmd| (state.commands.get(cmd).copied().unwrap_or(0), cmd)),
+        );
+    }
+
+    let longest_waits: Vec<_> = most_common(&state.command_durations)
+        .into_iter()
+        .filter(|x| x.0 > 0)
+        .take(display.waits)
+        .collect();
+    if !longest_waits.is_empty() {
+        println!();
+        f.heading("Commands you spent the most time waiting on:");
+        f.list(
+            "Command",
+            longest_waits
+                .into_iter()
+                .map(|(secs, cmd)| (format_duration(secs), cmd)),
+        );
+    }
+
+    for analyzer in &state.analyzers {
+        let mut display = display;
+        // analyzers without a limit of their own list as many as the top commands
+        let limit = analyzer
+            .name()
+            .and_then(|x| display.section(x).copied())
+            .unwrap_or(display.commands);
+        for section in analyzer.report(limit) {
+            println!();
+            f.heading(section.heading);
+            f.list(section.column, section.rows);
+            for line in section.lines {
+                f.line(f.plain(line));
+            }
+        }
     }
-    println!("... maybe consider sponsoring them?");
 }
@@ -0,0 +1,32 @@
+//! Spreading independent pieces of work over a fixed number of threads.
+
+use std::{sync::Mutex, thread};
+
+/// Applies `f` to every item on up to `jobs` threads and returns the results in the order of
+/// `items`. The threads take the next item as soon as they are done with one, so items that take
+/// much longer than the others do not hold up the rest.
+pub fn map<T: Send, R: Send>(jobs: usize, items: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let len = items.len();
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new((0..len).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(len) {
+            scope.spawn(|| loop {
+                let Some((i, item)) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.expect("every item was processed"))
+        .collect()
+}
@@ -0,0 +1,362 @@
+//! On-disk store for `--persist`, so statistics accumulate across runs, and the state files of
+//! `--save-state` and `--load-state`.
+//!
+//! Values are written in a compact binary encoding. Structs are stored as named [`Fields`], which lets
+//! a store written by an older version be read after new statistics were added: missing fields fall
+//! back to their defaults.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{HistoryEntry, State};
+
+const MAGIC: &[u8] = b"linux_wrapped stats\n";
+const STATE_MAGIC: &[u8] = b"linux_wrapped state\n";
+
+pub trait Persist: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if input.len() < n {
+        return None;
+    }
+    let (head, tail) = input.split_at(n);
+    *input = tail;
+    Some(head)
+}
+
+macro_rules! impl_persist_int {
+    ($($t:ty),*) => {
+        $(impl Persist for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend(self.to_le_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let bytes = take(input, std::mem::size_of::<Self>())?;
+                Some(Self::from_le_bytes(bytes.try_into().ok()?))
+            }
+        })*
+    };
+}
+
+impl_persist_int!(u8, u32, u64, i32, i64);
+
+impl Persist for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_bits().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        u64::decode(input).map(Self::from_bits)
+    }
+}
+
+impl Persist for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        u64::decode(input)?.try_into().ok()
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl Persist for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(x) => {
+                out.push(1);
+                x.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(None),
+            _ => T::decode(input).map(Some),
+        }
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<A: Persist, B: Persist, C: Persist> Persist for (A, B, C) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+        self.2.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(input)?, B::decode(input)?, C::decode(input)?))
+    }
+}
+
+impl<T: Persist, const N: usize> Persist for [T; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let items: Vec<_> = (0..N).map(|_| T::decode(input)).collect::<Option<_>>()?;
+        items.try_into().ok()
+    }
+}
+
+impl<K: Persist + Eq + Hash, V: Persist> Persist for HashMap<K, V> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for (key, value) in self {
+            key.encode(out);
+            value.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        (0..len)
+            .map(|_| Some((K::decode(input)?, V::decode(input)?)))
+            .collect()
+    }
+}
+
+/// Named, individually encoded values.
+#[derive(Default)]
+pub struct Fields(HashMap<String, Vec<u8>>);
+
+impl Fields {
+    pub fn set(&mut self, name: &str, value: &impl Persist) {
+        let mut out = Vec::new();
+        value.encode(&mut out);
+        self.0.insert(name.to_owned(), out);
+    }
+
+    /// The value stored under `name`, or the default if there is none. `None` if it cannot be decoded.
+    pub fn get<T: Persist + Default>(&self, name: &str) -> Option<T> {
+        match self.0.get(name) {
+            Some(bytes) => T::decode(&mut bytes.as_slice()),
+            None => Some(T::default()),
+        }
+    }
+}
+
+impl Persist for Fields {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        HashMap::decode(input).map(Self)
+    }
+}
+
+/// How far a history file was counted by earlier runs. Shells drop the oldest entries once the
+/// history is full, so the number of entries counted says nothing about where to continue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    /// the newest timestamp counted, and how many entries with that timestamp were counted
+    Timestamp(u64, u64),
+    /// for a history without timestamps, the number of entries counted and the last of them,
+    /// which is unknown for stores written before cursors were kept
+    Position(u64, Option<Vec<u8>>),
+}
+
+impl Cursor {
+    /// The `entries` of a history that come after `cursor`, and the cursor to continue from next
+    /// time. Entries are taken to be in chronological order, and those without a timestamp to be
+    /// from the time of the one before them. If the last entry counted in a history without
+    /// timestamps is no longer where it was, the history was trimmed at the front and counting
+    /// continues after the last occurrence of its command line.
+    pub fn unseen(
+        cursor: Option<&Self>,
+        mut entries: Vec<HistoryEntry>,
+    ) -> (Vec<HistoryEntry>, Option<Self>) {
+        let start = match cursor {
+            None => 0,
+            Some(Self::Timestamp(newest, counted)) => {
+                let (mut timestamp, mut same) = (0, 0);
+                entries
+                    .iter()
+                    .position(|entry| {
+                        timestamp = entry.timestamp.unwrap_or(timestamp);
+                        same += u64::from(timestamp == *newest);
+                        timestamp > *newest || (timestamp == *newest && same > *counted)
+                    })
+                    .unwrap_or(entries.len())
+            }
+            Some(Self::Position(counted, last)) => {
+                let counted = (*counted as usize).min(entries.len());
+                match last {
+                    Some(last) if counted > 0 && entries[counted - 1].command != *last => entries
+                        .iter()
+                        .rposition(|x| x.command == *last)
+                        .map_or(0, |x| x + 1),
+                    _ => counted,
+                }
+            }
+        };
+        let next = if entries.is_empty() {
+            cursor.cloned()
+        } else if entries.iter().any(|x| x.timestamp.is_some()) {
+            let mut timestamp = 0;
+            let timestamps: Vec<u64> = entries
+                .iter()
+                .map(|x| {
+                    timestamp = x.timestamp.unwrap_or(timestamp);
+                    timestamp
+                })
+                .collect();
+            let newest = timestamps[timestamps.len() - 1];
+            let same = timestamps.iter().filter(|x| **x == newest).count();
+            Some(Self::Timestamp(newest, same as u64))
+        } else {
+            let last = entries[entries.len() - 1].command.clone();
+            Some(Self::Position(entries.len() as u64, Some(last)))
+        };
+        (entries.split_off(start), next)
+    }
+}
+
+impl Persist for Cursor {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Timestamp(newest, counted) => {
+                out.push(0);
+                newest.encode(out);
+                counted.encode(out);
+            }
+            Self::Position(counted, last) => {
+                out.push(1);
+                counted.encode(out);
+                last.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Self::Timestamp(u64::decode(input)?, u64::decode(input)?)),
+            1 => Some(Self::Position(u64::decode(input)?, Option::decode(input)?)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Store {
+    /// how far each history file was counted
+    pub sources: HashMap<String, Cursor>,
+    pub state: State,
+}
+
+impl Store {
+    pub fn default_path() -> Option<PathBuf> {
+        let mut path = home::home_dir()?;
+        path.extend([".local", "share", "linux_wrapped", "stats.db"]);
+        Some(path)
+    }
+
+    /// Reads the store at `path`, starting from scratch if it does not exist yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a linux_wrapped store");
+        let fields: Fields = decode_file(&bytes, MAGIC).ok_or_else(invalid)?;
+        // older stores only kept the number of entries counted
+        let counts: HashMap<String, u64> = fields.get("sources").ok_or_else(invalid)?;
+        let mut sources: HashMap<String, Cursor> = counts
+            .into_iter()
+            .map(|(path, counted)| (path, Cursor::Position(counted, None)))
+            .collect();
+        sources.extend(fields.get::<HashMap<_, _>>("cursors").ok_or_else(invalid)?);
+        Ok(Self {
+            sources,
+            state: fields.get("state").ok_or_else(invalid)?,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut fields = Fields::default();
+        fields.set("cursors", &self.sources);
+        fields.set("state", &self.state);
+        write_file(path, MAGIC, &fields)
+    }
+}
+
+fn decode_file<T: Persist>(bytes: &[u8], magic: &[u8]) -> Option<T> {
+    T::decode(&mut bytes.strip_prefix(magic)?)
+}
+
+fn write_file(path: &Path, magic: &[u8], value: &impl Persist) -> io::Result<()> {
+    let mut out = magic.to_vec();
+    value.encode(&mut out);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // write to a temporary file first so an interrupted run cannot corrupt the file
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, out)?;
+    std::fs::rename(tmp, path)
+}
+
+impl State {
+    /// Writes the statistics to `path`, to be read again with [`State::load`].
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        write_file(path, STATE_MAGIC, self)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        decode_file(&bytes, STATE_MAGIC)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a linux_wrapped state"))
+    }
+}
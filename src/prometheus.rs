@@ -0,0 +1,266 @@
+//! The statistics in the Prometheus text exposition format, for `--metrics-format prometheus`.
+//!
+//! Every statistic is its own metric family of gauges, labelled by what was counted, e.g.
+//! `linux_wrapped_command_count{command="git"} 1234`.
+
+use std::{collections::HashMap, fmt::Display, fmt::Write};
+
+use linux_wrapped::{compute_sessions, config::Config, most_common, State};
+
+const PREFIX: &str = "linux_wrapped_";
+
+/// Escapes a label value, which is written in double quotes.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+fn family(out: &mut String, name: &str, help: &str) {
+    writeln!(out, "# HELP {PREFIX}{name} {help}").unwrap();
+    writeln!(out, "# TYPE {PREFIX}{name} gauge").unwrap();
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    write!(out, "{PREFIX}{name}").unwrap();
+    if !labels.is_empty() {
+        let labels: Vec<_> = labels
+            .iter()
+            .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+            .collect();
+        write!(out, "{{{}}}", labels.join(",")).unwrap();
+    }
+    writeln!(out, " {value}").unwrap();
+}
+
+/// A family of counts keyed by a single label, largest first.
+fn counts<V: Ord + Copy + Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    map: &HashMap<String, V>,
+) {
+    if map.is_empty() {
+        return;
+    }
+    family(out, name, help);
+    for (count, key) in most_common(map) {
+        sample(out, name, &[(label, key)], count);
+    }
+}
+
+pub fn render(state: &State, config: &Config) -> String {
+    let mut out = String::new();
+    for (name, help, value) in [
+        (
+            "commands",
+            "Number of command lines in the history.",
+            state.total_commands,
+        ),
+        (
+            "sessions",
+            "Number of terminal sessions.",
+            compute_sessions(&state.entry_timestamps, config.session_gap()).len() as u64,
+        ),
+        (
+            "sudo_count",
+            "Commands run through sudo or doas.",
+            state.sudo_count,
+        ),
+        (
+            "background_jobs",
+            "Command lines run in the background.",
+            state.background_jobs,
+        ),
+        (
+            "undo_count",
+            "Undo operations like git reset and rm -rf.",
+            state.undo_count,
+        ),
+        (
+            "destructive_commands",
+            "Runs of rm -rf, truncate and dd.",
+            state.destructive_commands,
+        ),
+    ] {
+        family(&mut out, name, help);
+        sample(&mut out, name, &[], value);
+    }
+
+    let maps = [
+        (
+            "command_count",
+            "How often each command was run.",
+            "command",
+            &state.commands,
+        ),
+        (
+            "git_subcommand_count",
+            "How often each git subcommand was run.",
+            "subcommand",
+            &state.git_subcommands,
+        ),
+        (
+            "cargo_subcommand_count",
+            "How often each cargo subcommand was run.",
+            "subcommand",
+            &state.cargo_subcommands,
+        ),
+        (
+            "package_manager_op_count",
+            "Package manager operations.",
+            "operation",
+            &state.package_manager_ops,
+        ),
+        (
+            "npm_op_count",
+            "JavaScript package manager operations.",
+            "operation",
+            &state.npm_ops,
+        ),
+        (
+            "kubectl_op_count",
+            "kubectl operations.",
+            "operation",
+            &state.k8s_ops,
+        ),
+        (
+            "systemctl_op_count",
+            "systemd unit operations.",
+            "operation",
+            &state.systemctl_ops,
+        ),
+        (
+            "cloud_op_count",
+            "Operations of the aws, gcloud, az and terraform CLIs.",
+            "operation",
+            &state.cloud_ops,
+        ),
+        (
+            "build_target_count",
+            "Build targets run with make, ninja, cmake and meson.",
+            "target",
+            &state.build_targets,
+        ),
+        (
+            "ssh_host_count",
+            "Connections per SSH host.",
+            "host",
+            &state.ssh_hosts,
+        ),
+        (
+            "http_host_count",
+            "curl and wget requests per host.",
+            "host",
+            &state.http_targets,
+        ),
+        (
+            "directory_count",
+            "Visits per directory.",
+            "directory",
+            &state.directories,
+        ),
+        (
+            "editor_count",
+            "Uses of each editor.",
+            "editor",
+            &state.editors,
+        ),
+        (
+            "search_tool_count",
+            "Uses of each search tool.",
+            "tool",
+            &state.search_tools,
+        ),
+        (
+            "backgrounded_command_count",
+            "How often each command was run in the background.",
+            "command",
+            &state.backgrounded_commands,
+        ),
+    ];
+    for (name, help, label, map) in maps {
+        counts(&mut out, name, help, label, map);
+    }
+    counts(
+        &mut out,
+        "command_duration_seconds",
+        "Total time spent in each command.",
+        "command",
+        &state.command_durations,
+    );
+
+    if !state.man_pages.is_empty() {
+        let name = "man_page_count";
+        family(&mut out, name, "How often each man page was opened.");
+        for (count, (section, page)) in most_common(&state.man_pages) {
+            let section = section.to_string();
+            sample(
+                &mut out,
+                name,
+                &[("page", page), ("section", &section)],
+                count,
+            );
+        }
+    }
+    if !state.docker_ops.is_empty() {
+        let name = "docker_op_count";
+        family(
+            &mut out,
+            name,
+            "Docker subcommands, with the image if there is one.",
+        );
+        for (count, (subcommand, image)) in most_common(&state.docker_ops) {
+            let image = image.as_deref().unwrap_or_default();
+            sample(
+                &mut out,
+                name,
+                &[("subcommand", subcommand), ("image", image)],
+                count,
+            );
+        }
+    }
+    if !state.command_bigrams.is_empty() {
+        let name = "command_pair_count";
+        family(&mut out, name, "How often one command followed another.");
+        for (count, (first, second)) in most_common(&state.command_bigrams) {
+            sample(
+                &mut out,
+                name,
+                &[("first", first), ("second", second)],
+                count,
+            );
+        }
+    }
+
+    let name = "hourly_command_count";
+    family(
+        &mut out,
+        name,
+        "Commands started in each hour of the day, local time.",
+    );
+    for (hour, count) in state.hourly_counts.iter().enumerate() {
+        sample(&mut out, name, &[("hour", &hour.to_string())], count);
+    }
+    let name = "weekday_command_count";
+    family(
+        &mut out,
+        name,
+        "Commands started on each day of the week, local time.",
+    );
+    let weekdays = [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ];
+    for (weekday, count) in weekdays.iter().zip(state.weekday_counts) {
+        sample(&mut out, name, &[("weekday", weekday)], count);
+    }
+    out
+}
@@ -0,0 +1,83 @@
+//! Summary statistics over command counts.
+
+use std::collections::HashMap;
+
+use crate::time;
+
+/// How unpredictable the next command is, in bits. 0 if only one command was ever used.
+pub fn shannon_entropy(counts: &HashMap<String, u32>) -> f64 {
+    let total: f64 = counts.values().map(|x| f64::from(*x)).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    counts
+        .values()
+        .filter(|x| **x > 0)
+        .map(|x| {
+            let p = f64::from(*x) / total;
+            // rather than `-p * p.log2()`, which is -0 for a single command
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// How unevenly the uses are spread, from 0 if every command was used equally often to almost 1 if
+/// nearly all uses are of a single command.
+pub fn gini(counts: &[u32]) -> f64 {
+    let mut sorted = counts.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len() as f64;
+    let total: f64 = sorted.iter().map(|x| f64::from(*x)).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (i + 1) as f64 * f64::from(*x))
+        .sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+/// How well the counts, sorted from the most frequent, follow Zipf's law: the R² of a straight
+/// line through log(rank) and log(count), from 0 to 1. 0 if there are fewer than three counts or
+/// they are all equal, since then there is nothing to fit.
+pub fn zipf_fit(sorted_counts: &[u32]) -> f64 {
+    let points: Vec<(f64, f64)> = sorted_counts
+        .iter()
+        .filter(|x| **x > 0)
+        .enumerate()
+        .map(|(i, x)| (((i + 1) as f64).ln(), f64::from(*x).ln()))
+        .collect();
+    if points.len() < 3 {
+        return 0.0;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|x| x.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|x| x.1).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in &points {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    if variance_y == 0.0 {
+        return 0.0;
+    }
+    covariance.powi(2) / (variance_x * variance_y)
+}
+
+/// The local hour of day of the busiest hour in `hourly_data`, as returned by
+/// [`crate::hourly_buckets`], and how many commands per minute were run in it.
+pub fn peak_hour(hourly_data: &[(u64, u64)]) -> Option<(u8, f64)> {
+    rate(hourly_data.iter().max_by_key(|x| x.1)?)
+}
+
+/// Like [`peak_hour`], but for the hour with the fewest commands among those with any.
+pub fn quietest_hour(hourly_data: &[(u64, u64)]) -> Option<(u8, f64)> {
+    rate(hourly_data.iter().filter(|x| x.1 > 0).min_by_key(|x| x.1)?)
+}
+
+fn rate(&(start, count): &(u64, u64)) -> Option<(u8, f64)> {
+    Some((time::local_time(start)?.hour, count as f64 / 60.0))
+}
@@ -0,0 +1,106 @@
+//! Output templates for `--format`, like `"{rank}. {command} ({count}×, {percent}%)"`.
+//!
+//! A template is text with `{variable}` placeholders that is repeated for every command in the
+//! ranking. `{{` and `}}` stand for literal braces.
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Command,
+    Count,
+    /// position in the ranking, starting at 1
+    Rank,
+    /// share of all commands, with one decimal
+    Percent,
+}
+
+impl Variable {
+    const ALL: [(&'static str, Self); 4] = [
+        ("command", Self::Command),
+        ("count", Self::Count),
+        ("rank", Self::Rank),
+        ("percent", Self::Percent),
+    ];
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|x| x.0 == name).map(|x| x.1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Variable(Variable),
+}
+
+/// A parsed template, see the module documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Piece>);
+
+/// What the variables of a template stand for in one line of the output.
+pub struct Row<'a> {
+    pub command: &'a str,
+    pub count: u32,
+    pub rank: usize,
+    /// the total number of commands, so the share of `count` can be computed
+    pub total: u64,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unterminated `{{{name}`")),
+                        }
+                    }
+                    let variable = Variable::parse(name.trim()).ok_or_else(|| {
+                        let known: Vec<_> = Variable::ALL.iter().map(|x| x.0).collect();
+                        format!(
+                            "unknown variable `{{{name}}}`, expected one of {{{}}}",
+                            known.join("}, {")
+                        )
+                    })?;
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Variable(variable));
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a literal brace".into()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Self(pieces))
+    }
+
+    pub fn render(&self, row: &Row) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Variable(Variable::Command) => out.push_str(row.command),
+                Piece::Variable(Variable::Count) => write!(out, "{}", row.count).unwrap(),
+                Piece::Variable(Variable::Rank) => write!(out, "{}", row.rank).unwrap(),
+                Piece::Variable(Variable::Percent) => {
+                    let share = f64::from(row.count) / row.total.max(1) as f64 * 100.0;
+                    write!(out, "{share:.1}").unwrap()
+                }
+            }
+        }
+        out
+    }
+}
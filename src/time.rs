@@ -0,0 +1,156 @@
+//! Local time conversion for history timestamps, using the C library so the user's timezone is respected.
+
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+pub const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+pub struct LocalTime {
+    pub date: Date,
+    pub hour: u8,
+    /// days since Monday, indexes into [`WEEKDAYS`]
+    pub weekday: u8,
+}
+
+pub fn local_time(timestamp: u64) -> Option<LocalTime> {
+    let t = libc::time_t::try_from(timestamp).ok()?;
+    // SAFETY: `tm` is a plain C struct for which all-zero is a valid value, and `localtime_r` only
+    // writes to the buffer we hand it
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return None;
+    }
+    Some(LocalTime {
+        date: Date {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u8 + 1,
+            day: tm.tm_mday as u8,
+        },
+        hour: tm.tm_hour as u8,
+        weekday: ((tm.tm_wday + 6) % 7) as u8,
+    })
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+/// A calendar date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+}
+
+impl Date {
+    /// Parses an ISO 8601 date like `2024-01-31`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.splitn(3, '-');
+        let mut part = |len| {
+            parts
+                .next()
+                .filter(|x| x.len() == len && x.bytes().all(|x| x.is_ascii_digit()))
+        };
+        let date = Self {
+            year: part(4)?.parse().ok()?,
+            month: part(2)?.parse().ok()?,
+            day: part(2)?.parse().ok()?,
+        };
+        (1..=12).contains(&date.month).then_some(())?;
+        (1..=date.days_in_month())
+            .contains(&date.day)
+            .then_some(date)
+    }
+
+    fn days_in_month(self) -> u8 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// The unix timestamp of local midnight at the start of this day.
+    pub fn start_of_day(self) -> Option<u64> {
+        // SAFETY: as in `local_time`; `mktime` only reads and normalizes the struct we hand it
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        tm.tm_year = self.year - 1900;
+        tm.tm_mon = i32::from(self.month) - 1;
+        tm.tm_mday = i32::from(self.day);
+        // let the C library figure out whether daylight saving time applies
+        tm.tm_isdst = -1;
+        let t = unsafe { libc::mktime(&mut tm) };
+        u64::try_from(t).ok()
+    }
+
+    /// Days since 1970-01-01, see <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+    pub fn day_number(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// The ISO 8601 year and week number, in which weeks start on Monday and the first week of a
+    /// year is the one containing its first Thursday.
+    pub fn iso_week(self) -> (i32, u8) {
+        let days = self.day_number();
+        // 1970-01-01 was a Thursday
+        let thursday = days - (days + 3).rem_euclid(7) + 3;
+        let year = Self::from_day_number(thursday).year;
+        let new_year = Self {
+            year,
+            month: 1,
+            day: 1,
+        };
+        (year, ((thursday - new_year.day_number()) / 7 + 1) as u8)
+    }
+
+    /// The inverse of [`Date::day_number`], see
+    /// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    pub fn from_day_number(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        Self {
+            year: (year_of_era + era * 400 + i64::from(month <= 2)) as i32,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
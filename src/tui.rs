@@ -0,0 +1,302 @@
+//! Full-screen terminal UI for `--tui`, drawn with plain ANSI escape sequences.
+//!
+//! Left and right switch tabs, up and down (or `j` and `k`), page up and page down scroll, `/`
+//! filters the list and `q` quits.
+
+use std::{
+    io::{self, IsTerminal, Read, Write},
+    os::fd::AsRawFd,
+};
+
+use linux_wrapped::{
+    daily_counts, fuzzy_match, graph::render_ascii_graph, man_page_label, most_common, State,
+};
+
+/// Puts the terminal into raw mode on the alternate screen, and restores it when dropped.
+struct Terminal {
+    original: libc::termios,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        let fd = io::stdin().as_raw_fd();
+        // SAFETY: `termios` is a plain C struct that `tcgetattr` fills in
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // alternate screen, hidden cursor
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+        Ok(Self { original })
+    }
+
+    /// Columns and rows, with a fallback for terminals that do not say.
+    fn size() -> (usize, usize) {
+        // SAFETY: `winsize` is a plain C struct that `ioctl` fills in
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let fd = io::stdout().as_raw_fd();
+        if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } != 0 || size.ws_col == 0 {
+            return (80, 24);
+        }
+        (size.ws_col.into(), size.ws_row.into())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        unsafe { libc::tcsetattr(io::stdin().as_raw_fd(), libc::TCSANOW, &self.original) };
+    }
+}
+
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Enter,
+    Backspace,
+    Escape,
+    Other,
+}
+
+/// The keys pressed since the last call. Several arrive at once when typing fast or pasting.
+fn read_keys() -> io::Result<Vec<Key>> {
+    let mut buf = [0; 64];
+    let n = io::stdin().read(&mut buf)?;
+    if n == 0 {
+        return Ok(vec![Key::Char('q')]);
+    }
+    let mut keys = Vec::new();
+    let mut rest = &buf[..n];
+    while !rest.is_empty() {
+        let sequences: [(&[u8], Key); 10] = [
+            (b"\x1b[A", Key::Up),
+            (b"\x1bOA", Key::Up),
+            (b"\x1b[B", Key::Down),
+            (b"\x1bOB", Key::Down),
+            (b"\x1b[C", Key::Right),
+            (b"\x1bOC", Key::Right),
+            (b"\x1b[D", Key::Left),
+            (b"\x1bOD", Key::Left),
+            (b"\x1b[5~", Key::PageUp),
+            (b"\x1b[6~", Key::PageDown),
+        ];
+        if let Some((sequence, key)) = sequences.into_iter().find(|x| rest.starts_with(x.0)) {
+            keys.push(key);
+            rest = &rest[sequence.len()..];
+            continue;
+        }
+        let len = match rest[0] {
+            0xc0.. => rest[0].leading_ones() as usize,
+            _ => 1,
+        };
+        let (key, tail) = rest.split_at(len.min(rest.len()));
+        rest = tail;
+        keys.push(match key {
+            b"\x1b" => Key::Escape,
+            b"\r" | b"\n" => Key::Enter,
+            b"\x7f" | b"\x08" => Key::Backspace,
+            // Ctrl-C, since raw mode does not turn it into a signal
+            b"\x03" => Key::Char('q'),
+            key => match std::str::from_utf8(key).ok().and_then(|x| x.chars().next()) {
+                Some(c) => Key::Char(c),
+                None => Key::Other,
+            },
+        });
+    }
+    Ok(keys)
+}
+
+struct Tab {
+    title: &'static str,
+    rows: Vec<String>,
+}
+
+fn count_rows(entries: impl IntoIterator<Item = (u32, String)>) -> Vec<String> {
+    entries
+        .into_iter()
+        .map(|(count, name)| format!("{count:>8}  {name}"))
+        .collect()
+}
+
+fn tabs(state: &State, width: usize, height: usize) -> Vec<Tab> {
+    let graph = render_ascii_graph(
+        &daily_counts(&state.entry_timestamps),
+        width.saturating_sub(12),
+        height.saturating_sub(8).max(4),
+    );
+    vec![
+        Tab {
+            title: "Commands",
+            rows: count_rows(
+                most_common(&state.commands)
+                    .into_iter()
+                    .map(|(count, x)| (count, x.clone())),
+            ),
+        },
+        Tab {
+            title: "Git",
+            rows: count_rows(
+                most_common(&state.git_subcommands)
+                    .into_iter()
+                    .map(|(count, x)| (count, x.clone())),
+            ),
+        },
+        Tab {
+            title: "Man Pages",
+            rows: count_rows(
+                most_common(&state.man_pages)
+                    .into_iter()
+                    .map(|(count, (section, page))| (count, man_page_label(*section, page))),
+            ),
+        },
+        Tab {
+            title: "Timeline",
+            rows: graph.lines().map(str::to_owned).collect(),
+        },
+        Tab {
+            title: "Bigrams",
+            rows: count_rows(
+                most_common(&state.command_bigrams)
+                    .into_iter()
+                    .map(|(count, (first, second))| (count, format!("{first} → {second}"))),
+            ),
+        },
+    ]
+}
+
+/// Cuts `line` down to `width` characters.
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+struct App {
+    tabs: Vec<Tab>,
+    selected: usize,
+    /// first visible row of the filtered list
+    scroll: usize,
+    filter: String,
+    editing_filter: bool,
+}
+
+impl App {
+    fn visible_rows(&self) -> Vec<&str> {
+        self.tabs[self.selected]
+            .rows
+            .iter()
+            .map(String::as_str)
+            .filter(|x| fuzzy_match(&self.filter, x))
+            .collect()
+    }
+
+    fn draw(&self, width: usize, height: usize) -> String {
+        let mut out = String::from("\x1b[H\x1b[2J");
+        let mut header = String::new();
+        for (i, tab) in self.tabs.iter().enumerate() {
+            if i == self.selected {
+                header.push_str(&format!("\x1b[7m {} \x1b[0m ", tab.title));
+            } else {
+                header.push_str(&format!(" {}  ", tab.title));
+            }
+        }
+        out.push_str(&header);
+        out.push_str("\r\n");
+        let filter = match (self.editing_filter, self.filter.is_empty()) {
+            (true, _) => format!("/{}_", self.filter),
+            (false, false) => format!("filter: {}", self.filter),
+            (false, true) => String::new(),
+        };
+        out.push_str(&truncate(&filter, width));
+        out.push_str("\r\n");
+
+        let rows = self.visible_rows();
+        let list_height = height.saturating_sub(3);
+        for row in rows.iter().skip(self.scroll).take(list_height) {
+            out.push_str(&truncate(row, width));
+            out.push_str("\r\n");
+        }
+        let help = "←/→ tab  ↑/↓ scroll  / filter  q quit";
+        let position = format!("{}/{}", (self.scroll + 1).min(rows.len()), rows.len());
+        out.push_str(&format!("\x1b[{height};1H\x1b[2m"));
+        out.push_str(&truncate(&format!("{position}  {help}"), width));
+        out.push_str("\x1b[0m");
+        out
+    }
+
+    /// Applies a key press, returning `false` to quit.
+    fn handle(&mut self, key: Key, height: usize) -> bool {
+        let page = height.saturating_sub(3).max(1);
+        let last = self.visible_rows().len().saturating_sub(1);
+        if self.editing_filter {
+            match key {
+                Key::Enter | Key::Escape => self.editing_filter = false,
+                Key::Backspace => {
+                    self.filter.pop();
+                }
+                Key::Char(c) if !c.is_control() => self.filter.push(c),
+                _ => {}
+            }
+            self.scroll = 0;
+            return true;
+        }
+        match key {
+            Key::Char('q') => return false,
+            Key::Char('/') => {
+                self.editing_filter = true;
+                self.filter.clear();
+            }
+            Key::Escape => self.filter.clear(),
+            Key::Left | Key::Char('h') => {
+                self.selected = (self.selected + self.tabs.len() - 1) % self.tabs.len();
+                self.scroll = 0;
+            }
+            Key::Right | Key::Char('l') | Key::Char('\t') => {
+                self.selected = (self.selected + 1) % self.tabs.len();
+                self.scroll = 0;
+            }
+            Key::Up | Key::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.scroll = (self.scroll + 1).min(last),
+            Key::PageUp => self.scroll = self.scroll.saturating_sub(page),
+            Key::PageDown => self.scroll = (self.scroll + page).min(last),
+            _ => {}
+        }
+        true
+    }
+}
+
+pub fn run(state: &State) -> io::Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return Err(io::Error::other("--tui needs a terminal"));
+    }
+    let (width, height) = Terminal::size();
+    let mut app = App {
+        tabs: tabs(state, width, height),
+        selected: 0,
+        scroll: 0,
+        filter: String::new(),
+        editing_filter: false,
+    };
+    let _terminal = Terminal::enter()?;
+    loop {
+        // the size is checked on every redraw, so resizing takes effect with the next key
+        let (width, height) = Terminal::size();
+        print!("{}", app.draw(width, height));
+        io::stdout().flush()?;
+        for key in read_keys()? {
+            if !app.handle(key, height) {
+                return Ok(());
+            }
+        }
+    }
+}
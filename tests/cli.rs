@@ -0,0 +1,31 @@
+//! Runs the program on the sample histories in `tests/fixtures`.
+
+use std::{path::PathBuf, process::Command};
+
+fn report(history: &str, args: &[&str]) -> String {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures");
+    // neither the config file nor the aliases of whoever runs the tests are read
+    let output = Command::new(env!("CARGO_BIN_EXE_linux_wrapped"))
+        .arg("--history-file")
+        .arg(fixtures.join(history))
+        .arg("--no-color")
+        .args(args)
+        .env("HOME", &fixtures)
+        .env("XDG_CONFIG_HOME", &fixtures)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn anonymize_hides_podman_images() {
+    let plain = report("podman_history", &[]);
+    assert!(plain.contains("Your top Podman images:\n2 registry.example.com/team/app:1.0\n"));
+
+    let anonymized = report("podman_history", &["--anonymize"]);
+    assert!(!anonymized.contains("registry.example.com"));
+    assert!(anonymized.contains("Your top Podman images:\n2 <path>\n1 alpine\n"));
+}
@@ -0,0 +1,106 @@
+//! Parses the sample histories in `tests/fixtures`, written the way the shells write them.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use linux_wrapped::{
+    config::Config, open_history_file, process_command_history, HistoryEntry, HistoryFormat, State,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn entries(name: &str) -> Vec<HistoryEntry> {
+    open_history_file(&fixture(name), false).unwrap().collect()
+}
+
+fn process(name: &str) -> State {
+    let mut history = open_history_file(&fixture(name), false).unwrap();
+    let mut state = State::default();
+    process_command_history(&mut state, &mut history, &Config::default());
+    state
+}
+
+fn count<K: std::hash::Hash + Eq>(map: &HashMap<K, u32>, key: &K) -> u32 {
+    map.get(key).copied().unwrap_or(0)
+}
+
+#[test]
+fn zsh() {
+    assert_eq!(
+        HistoryFormat::detect(&fixture("zsh_history")).unwrap(),
+        HistoryFormat::Zsh
+    );
+    for mmap in [false, true] {
+        let entries: Vec<_> = open_history_file(&fixture("zsh_history"), mmap)
+            .unwrap()
+            .collect();
+        assert_eq!(entries.len(), 13);
+        assert_eq!(entries[0].timestamp, Some(1700000000));
+        assert_eq!(entries[6].elapsed.map(|x| x.as_secs()), Some(3));
+        assert_eq!(entries[8].command, b"for f in *.rs; do\n  wc -l $f\ndone");
+    }
+
+    let state = process("zsh_history");
+    assert_eq!(state.total_commands, 13);
+    assert_eq!(count(&state.git_subcommands, &"commit".to_owned()), 3);
+    assert_eq!(count(&state.git_subcommands, &"status".to_owned()), 1);
+    assert_eq!(count(&state.man_pages, &(0, "ls".to_owned())), 2);
+    assert_eq!(count(&state.commands, &"git".to_owned()), 6);
+    assert_eq!(state.sudo_count, 1);
+    assert_eq!(state.time_span, Some((1700000000, 1700000600)));
+}
+
+#[test]
+fn bash() {
+    assert_eq!(
+        HistoryFormat::detect(&fixture("bash_history")).unwrap(),
+        HistoryFormat::Bash
+    );
+    let entries = entries("bash_history");
+    // the empty line is skipped
+    assert_eq!(entries.len(), 8);
+    assert!(entries.iter().all(|x| x.timestamp.is_none()));
+
+    let state = process("bash_history");
+    assert_eq!(count(&state.commands, &"git".to_owned()), 3);
+    assert_eq!(count(&state.git_subcommands, &"status".to_owned()), 2);
+    assert_eq!(count(&state.man_pages, &(3, "printf".to_owned())), 1);
+    assert_eq!(state.time_span, None);
+}
+
+#[test]
+fn bash_with_timestamps() {
+    assert_eq!(
+        HistoryFormat::detect(&fixture("bash_history_timestamps")).unwrap(),
+        HistoryFormat::Bash
+    );
+    let entries = entries("bash_history_timestamps");
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[0].command, b"ls");
+    assert_eq!(entries[4].timestamp, Some(1700000240));
+
+    let state = process("bash_history_timestamps");
+    assert_eq!(count(&state.git_subcommands, &"status".to_owned()), 2);
+    assert_eq!(state.sudo_count, 1);
+    assert_eq!(state.time_span, Some((1700000000, 1700000240)));
+}
+
+#[test]
+fn fish() {
+    assert_eq!(
+        HistoryFormat::detect(&fixture("fish_history")).unwrap(),
+        HistoryFormat::Fish
+    );
+    let entries = entries("fish_history");
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[2].command, b"echo one\necho two");
+    assert_eq!(entries[3].timestamp, Some(1700000180));
+
+    let state = process("fish_history");
+    assert_eq!(count(&state.git_subcommands, &"checkout".to_owned()), 2);
+    assert_eq!(count(&state.man_pages, &(0, "fish".to_owned())), 1);
+}